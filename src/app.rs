@@ -1,25 +1,58 @@
 use egui_macroquad::egui::{Pos2, Rect};
 use macroquad::{
-    color::{Color, RED},
-    input::{MouseButton, is_mouse_button_down, mouse_position, mouse_position_local},
+    color::{Color, WHITE},
+    input::{KeyCode, MouseButton},
     miniquad::window::{screen_size, set_window_size},
-    window::{clear_background, next_frame, screen_height, screen_width},
+    shapes::draw_rectangle,
+    time::get_frame_time,
+    window::{clear_background, next_frame},
 };
 
 use crate::{
+    input::{Button, Input, InputSource, MacroquadInput},
     math::math::Vec2f,
     physics::{
+        boundary::Boundary,
+        electrostatics::PointCharge,
         entities::physics_body::{PhysicsBody, RigidBody},
+        force_field::{ForceField, ForceFieldId},
+        history::History,
+        joint::{Joint, JointId, MouseJoint},
+        material::{MATERIALS_PATH, Material, MaterialLibrary},
         physics_engine::PhysicsEngine,
     },
     renderer::{
-        entity::{Entity, EntityManager, Shape},
+        camera::Camera,
+        component::{ColliderComponent, TransformComponent},
+        entity::{Entity, EntityId, EntityManager, FIELD_HANDLE_RADIUS, Shape, draw_trajectory},
         ui::UiManager,
     },
+    schedule::{Schedule, Stage, SystemId},
+    timer::{Scheduler, TaskId},
 };
 
+// Raised during a physics step and readable by system functions on the next frame
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    // A joint pulled harder than its break force and was removed
+    JointBroken {
+        id: JointId,
+        a: EntityId,
+        b: EntityId,
+    },
+}
+
+impl Event {
+    pub fn involves(&self, id: EntityId) -> bool {
+        match *self {
+            Event::JointBroken { a, b, .. } => a == id || b == id,
+        }
+    }
+}
+
 pub struct AppContext {
     pub entity_manager: EntityManager,
+    pub input: Input,
     pub ui_wants_pointer: bool,
     pub ui_wants_keyboard: bool,
     pub ppu: f32,
@@ -27,69 +60,482 @@ pub struct AppContext {
     pub debug_outlines: bool,
     pub show_forces: bool,
     pub show_com: bool,
+    pub show_electric_field: bool,
     pub physics_dimensions: Vec2f,
+    pub camera: Camera,
+    pub boundary: Boundary,
     pub current_shape: Shape,
+    // Parameters the UI builds triangles, regular polygons and capsules from
+    pub shape_radius: f32,
+    pub shape_sides: usize,
+    pub shape_length: f32,
+    pub materials: MaterialLibrary,
+    pub current_material: String,
+    // Coulombs given to newly spawned entities
+    pub current_charge: f32,
+    pub dragged_field: Option<ForceFieldId>,
+    // A static body being moved while paused, with its offset from the cursor
+    pub dragged_body: Option<(EntityId, Vec2f)>,
+    // Newtons the mouse can pull a grabbed body with
+    pub grab_max_force: f32,
+    pub events: Vec<Event>,
+    // Bodies whose predicted path is drawn this frame, with the number of steps to look ahead
+    pub trajectory_previews: Vec<(Entity, usize)>,
+    // Simulated seconds, kept in step with the physics engine
+    pub time: f32,
+    pub scheduler: Scheduler,
 }
 
 impl AppContext {
+    // A context that does not need a window, with the default materials
+    pub fn init(physics_dimensions: Vec2f) -> Self {
+        Self {
+            entity_manager: EntityManager::init(),
+            input: Input::init(),
+            ui_wants_keyboard: false,
+            ui_wants_pointer: false,
+            side_panel_left_rect: Rect {
+                min: Pos2::new(0., 0.),
+                max: Pos2::new(0., 0.),
+            },
+            ppu: 100.,
+            debug_outlines: false,
+            physics_dimensions,
+            camera: Camera::new(physics_dimensions),
+            boundary: Boundary::init(),
+            current_shape: Shape::Circle,
+            shape_radius: 0.2,
+            shape_sides: 5,
+            shape_length: 0.4,
+            materials: MaterialLibrary::defaults(),
+            current_material: Material::default().name,
+            current_charge: 0.,
+            dragged_field: None,
+            dragged_body: None,
+            grab_max_force: 200.,
+            events: vec![],
+            trajectory_previews: vec![],
+            time: 0.,
+            scheduler: Scheduler::init(),
+            show_forces: false,
+            show_com: false,
+            show_electric_field: false,
+        }
+    }
+
     pub fn get_window_bounds() -> (f32, f32) {
         screen_size()
     }
 
-    pub fn get_button_press(&self, mouse_button: MouseButton) -> bool {
-        if self.ui_wants_pointer
+    fn pointer_over_ui(&self) -> bool {
+        self.ui_wants_pointer
             || self
                 .side_panel_left_rect
-                .contains(Pos2::from(mouse_position()))
+                .contains(Pos2::from(self.input.mouse_position))
+    }
+
+    // Whether the UI or a dragged force field or body is using the button
+    fn captured(&self, button: Button) -> bool {
+        match button {
+            Button::Key(_) => self.ui_wants_keyboard,
+            Button::Mouse(_) => {
+                self.pointer_over_ui()
+                    || self.dragged_field.is_some()
+                    || self.dragged_body.is_some()
+                    || self.entity_manager.mouse_joint.is_some()
+            }
+        }
+    }
+
+    pub fn get_button_press(&self, mouse_button: MouseButton) -> bool {
+        self.held(mouse_button)
+    }
+
+    // The button states below ignore input the UI is using, see Input for the raw states.
+    // Releases are always seen, so a drag that ends over the UI still ends
+    pub fn pressed(&self, button: impl Into<Button>) -> bool {
+        let button = button.into();
+        !self.captured(button) && self.input.pressed(button)
+    }
+
+    pub fn released(&self, button: impl Into<Button>) -> bool {
+        self.input.released(button)
+    }
+
+    pub fn held(&self, button: impl Into<Button>) -> bool {
+        let button = button.into();
+        !self.captured(button) && self.input.held(button)
+    }
+
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.input
+            .bindings(action)
+            .iter()
+            .any(|button| self.pressed(*button))
+    }
+
+    // Its last held button came up this frame
+    pub fn action_released(&self, action: &str) -> bool {
+        let bindings = self.input.bindings(action);
+        bindings.iter().any(|button| self.released(*button))
+            && !bindings.iter().any(|button| self.input.held(*button))
+    }
+
+    pub fn action_held(&self, action: &str) -> bool {
+        self.input
+            .bindings(action)
+            .iter()
+            .any(|button| self.held(*button))
+    }
+
+    // Wheel movement this frame, zero while scrolling the UI
+    pub fn mouse_wheel(&self) -> f32 {
+        if self.pointer_over_ui() {
+            return 0.;
+        }
+        self.input.mouse_wheel
+    }
+
+    pub fn add_force_field(&mut self, field: ForceField) -> ForceFieldId {
+        self.entity_manager.add_force_field(field)
+    }
+
+    pub fn add_point_charge(&mut self, position: Vec2f, charge: f32) {
+        self.entity_manager
+            .point_charges
+            .push(PointCharge { position, charge });
+    }
+
+    // None if either entity has no transform
+    pub fn add_revolute_joint(
+        &mut self,
+        a: EntityId,
+        b: EntityId,
+        anchor: Vec2f,
+    ) -> Option<JointId> {
+        let components = &self.entity_manager.components;
+        let transform_a = components.get::<TransformComponent>(&a)?;
+        let transform_b = components.get::<TransformComponent>(&b)?;
+        let joint = Joint::revolute((a, transform_a), (b, transform_b), anchor);
+        Some(self.entity_manager.add_joint(joint))
+    }
+
+    pub fn add_prismatic_joint(
+        &mut self,
+        a: EntityId,
+        b: EntityId,
+        anchor: Vec2f,
+        axis: Vec2f,
+    ) -> Option<JointId> {
+        let components = &self.entity_manager.components;
+        let transform_a = components.get::<TransformComponent>(&a)?;
+        let transform_b = components.get::<TransformComponent>(&b)?;
+        let joint = Joint::prismatic((a, transform_a), (b, transform_b), anchor, axis);
+        Some(self.entity_manager.add_joint(joint))
+    }
+
+    // Runs `callback` once, `delay` simulated seconds from now
+    pub fn after(&mut self, delay: f32, callback: impl FnMut(&mut AppContext) + 'static) -> TaskId {
+        self.scheduler.at(self.time + delay, callback)
+    }
+
+    // Runs `callback` every `period` simulated seconds
+    pub fn every(
+        &mut self,
+        period: f32,
+        callback: impl FnMut(&mut AppContext) + 'static,
+    ) -> TaskId {
+        self.scheduler.every(period, self.time, callback)
+    }
+
+    pub fn joint_mut(&mut self, id: JointId) -> Option<&mut Joint> {
+        self.entity_manager.joints.get_mut(&id)
+    }
+
+    // Picks up a force field when its handle is clicked and moves it with the mouse
+    fn drag_force_fields(&mut self) {
+        if !self.input.held(MouseButton::Left) {
+            self.dragged_field = None;
+            return;
+        }
+
+        let mouse_pos = self.get_mouse_position();
+        if self.input.pressed(MouseButton::Left) && !self.pointer_over_ui() {
+            self.dragged_field = self
+                .entity_manager
+                .force_fields
+                .iter()
+                .find(|(_, field)| (field.position - mouse_pos).length() <= FIELD_HANDLE_RADIUS)
+                .map(|(id, _)| *id);
+        }
+
+        if let Some(field) = self
+            .dragged_field
+            .and_then(|id| self.entity_manager.force_fields.get_mut(&id))
         {
-            return false;
+            field.position = mouse_pos;
+        }
+    }
+
+    // Left drag on a dynamic body pulls it with a mouse joint and lets go of it with whatever
+    // velocity it has, so it can be thrown. Other bodies are moved directly, only while paused
+    fn drag_bodies(&mut self, paused: bool) {
+        if !self.input.held(MouseButton::Left) {
+            self.entity_manager.mouse_joint = None;
+            self.dragged_body = None;
+            return;
+        }
+
+        let mouse_pos = self.get_mouse_position();
+        if self.pressed(MouseButton::Left)
+            && let Some(id) = self.entity_manager.entity_at(mouse_pos)
+        {
+            let components = &self.entity_manager.components;
+            let transform = components.get::<TransformComponent>(&id).unwrap();
+            if components
+                .get::<PhysicsBody>(&id)
+                .is_some_and(|body| body.is_dynamic())
+            {
+                let joint = MouseJoint::new((id, transform), mouse_pos, self.grab_max_force);
+                self.entity_manager.mouse_joint = Some(joint);
+            } else if paused {
+                self.dragged_body = Some((id, transform.position - mouse_pos));
+            }
+        }
+
+        if let Some(joint) = &mut self.entity_manager.mouse_joint {
+            joint.target = mouse_pos;
+        }
+        let Some((id, offset)) = self.dragged_body else {
+            return;
+        };
+        let components = &mut self.entity_manager.components;
+        match components.get_mut::<TransformComponent>(&id) {
+            Some(transform) if paused => {
+                transform.position = mouse_pos + offset;
+                let transform = *transform;
+                if let Some(collider) = components.get_mut::<ColliderComponent>(&id) {
+                    collider.update_bounding_box(&transform);
+                }
+            }
+            _ => self.dragged_body = None,
         }
-        is_mouse_button_down(mouse_button)
     }
 
     pub fn get_mouse_position(&self) -> Vec2f {
-        let (mx, my) = mouse_position();
+        let (mx, my) = self.input.mouse_position;
+        self.camera.to_world(Vec2f::new(mx, my))
+    }
+
+    // The world is drawn right of the side panel
+    fn update_viewport(&mut self) {
+        let (width, height) = self.input.screen_size;
+        let left = self.side_panel_left_rect.max.x.clamp(0., width);
+        self.camera
+            .set_viewport(Vec2f::new(left, 0.), Vec2f::new(width - left, height));
+    }
 
-        Vec2f {
-            x: mx / screen_width() * self.physics_dimensions.x,
-            y: (screen_height() - my) / screen_height() * self.physics_dimensions.y,
+    // Middle drag pans and the wheel zooms around the cursor
+    fn control_camera(&mut self) {
+        if self.held(MouseButton::Middle) {
+            let (dx, dy) = self.input.mouse_delta();
+            self.camera.pan(Vec2f::new(dx, dy));
+        }
+        let wheel = self.mouse_wheel();
+        if wheel != 0. {
+            let (mx, my) = self.input.mouse_position;
+            self.camera
+                .zoom_at(Vec2f::new(mx, my), ZOOM_STEP.powf(wheel.signum()));
+        }
+    }
+
+    // Centers the camera on the followed entity, and stops following it once it is gone
+    fn follow_camera(&mut self) {
+        let Some(id) = self.camera.follow else {
+            return;
+        };
+        match self
+            .entity_manager
+            .components
+            .get::<TransformComponent>(&id)
+        {
+            Some(transform) => self.camera.center = transform.position,
+            None => self.camera.follow = None,
         }
     }
 
     pub fn new_entity(
         &mut self,
         position: Vec2f,
-        mass: f32,
         size: Vec2f,
         color: Color,
         rigidbody: RigidBody,
-    ) {
-        let physics_body = PhysicsBody::new(position, mass, 0.3);
-        let ent = Entity::new(
-            size,
-            color,
-            physics_body,
-            self.current_shape.clone(),
-            rigidbody,
-        );
-        self.entity_manager.add(ent);
+    ) -> EntityId {
+        self.new_entity_shaped(position, size, color, self.current_shape.clone(), rigidbody)
     }
 
     pub fn new_entity_shaped(
         &mut self,
         position: Vec2f,
-        mass: f32,
         size: Vec2f,
         color: Color,
         shape: Shape,
         rigidbody: RigidBody,
+    ) -> EntityId {
+        let material = self.current_material.clone();
+        self.new_entity_material(position, size, color, shape, &material, rigidbody)
+    }
+
+    pub fn new_entity_material(
+        &mut self,
+        position: Vec2f,
+        size: Vec2f,
+        color: Color,
+        shape: Shape,
+        material: &str,
+        rigidbody: RigidBody,
+    ) -> EntityId {
+        let ent = self.build_entity(position, size, color, shape, material, rigidbody);
+        self.entity_manager.add(ent)
+    }
+
+    // Mass is derived from the shape's area and the material's density
+    fn build_entity(
+        &self,
+        position: Vec2f,
+        size: Vec2f,
+        color: Color,
+        shape: Shape,
+        material: &str,
+        rigidbody: RigidBody,
+    ) -> Entity {
+        let material = self.materials.get(material).cloned().unwrap_or_default();
+        let mass = material.density * shape.area(size);
+        let mut physics_body = PhysicsBody::new(mass, rigidbody);
+        physics_body.charge = self.current_charge;
+        Entity::new(position, size, color, shape, &material, physics_body)
+    }
+
+    // Draws the path the entity would follow if it moved at `velocity` now. Nothing for
+    // entities without a body
+    pub fn preview_trajectory(&mut self, id: EntityId, velocity: Vec2f, steps: usize) {
+        if let Some(mut entity) = self.entity_manager.get_entity(&id)
+            && let Some(body) = &mut entity.body
+        {
+            body.velocity = velocity;
+            self.trajectory_previews.push((entity, steps));
+        }
+    }
+
+    // Draws the path of a body new_entity would spawn, launched at `velocity`
+    pub fn preview_launch(
+        &mut self,
+        position: Vec2f,
+        size: Vec2f,
+        color: Color,
+        velocity: Vec2f,
+        steps: usize,
     ) {
-        let physics_body = PhysicsBody::new(position, mass, 0.3);
-        let ent = Entity::new(size, color, physics_body, shape, rigidbody);
-        self.entity_manager.add(ent);
+        let mut entity = self.build_entity(
+            position,
+            size,
+            color,
+            self.current_shape.clone(),
+            &self.current_material,
+            RigidBody::Dynamic,
+        );
+        if let Some(body) = &mut entity.body {
+            body.velocity = velocity;
+        }
+        self.trajectory_previews.push((entity, steps));
+    }
+
+    // Removes the entity with its joints and pending events. False if it was already gone
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if !self.entity_manager.remove(&id) {
+            return false;
+        }
+        self.events.retain(|event| !event.involves(id));
+        true
+    }
+
+    // Static terrain through `vertices` in world space
+    pub fn new_chain(
+        &mut self,
+        vertices: Vec<Vec2f>,
+        one_sided: bool,
+        color: Color,
+    ) -> Result<EntityId, String> {
+        let shape = Shape::chain(vertices, one_sided)?;
+        Ok(self.new_entity_shaped(
+            Vec2f::zero(),
+            Vec2f::zero(),
+            color,
+            shape,
+            RigidBody::Static,
+        ))
+    }
+
+    // The apply_* functions do nothing if the entity has no body or transform
+    fn body_mut(&mut self, id: EntityId) -> Option<(TransformComponent, &mut PhysicsBody)> {
+        let components = &mut self.entity_manager.components;
+        let transform = *components.get::<TransformComponent>(&id)?;
+        Some((transform, components.get_mut::<PhysicsBody>(&id)?))
+    }
+
+    pub fn apply_force(&mut self, id: EntityId, force: Vec2f) {
+        if let Some((_, body)) = self.body_mut(id) {
+            body.apply_force(force);
+        }
+    }
+
+    pub fn apply_force_at_point(&mut self, id: EntityId, force: Vec2f, point: Vec2f) {
+        if let Some((transform, body)) = self.body_mut(id) {
+            body.apply_force_at_point(&transform, force, point);
+        }
+    }
+
+    pub fn apply_impulse(&mut self, id: EntityId, impulse: Vec2f) {
+        if let Some((_, body)) = self.body_mut(id) {
+            body.apply_impulse(impulse);
+        }
+    }
+
+    pub fn apply_impulse_at_point(&mut self, id: EntityId, impulse: Vec2f, point: Vec2f) {
+        if let Some((transform, body)) = self.body_mut(id) {
+            body.apply_impulse_at_point(&transform, impulse, point);
+        }
+    }
+
+    pub fn apply_torque(&mut self, id: EntityId, torque: f32) {
+        if let Some((_, body)) = self.body_mut(id) {
+            body.apply_torque(torque);
+        }
+    }
+
+    // Applies `force` through the center of mass for `duration` seconds
+    pub fn apply_timed_force(&mut self, id: EntityId, force: Vec2f, duration: f32) {
+        if let Some((transform, body)) = self.body_mut(id) {
+            body.apply_timed_force(&transform, force, transform.position, duration);
+        }
+    }
+
+    pub fn apply_timed_force_at_point(
+        &mut self,
+        id: EntityId,
+        force: Vec2f,
+        point: Vec2f,
+        duration: f32,
+    ) {
+        if let Some((transform, body)) = self.body_mut(id) {
+            body.apply_timed_force(&transform, force, point, duration);
+        }
     }
 }
 
+// Zoom factor per notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.1;
+
 pub struct WindowParameters {
     pub width: u32,
     pub height: u32,
@@ -97,9 +543,12 @@ pub struct WindowParameters {
 
 pub struct App<S> {
     pub app_context: AppContext,
+    // Where input is read from every frame
+    pub input_source: Box<dyn InputSource>,
     pub physics_engine: PhysicsEngine,
-    pub systems: Vec<fn(&mut AppContext, f32, &mut S)>,
+    pub schedule: Schedule<S>,
     pub paused: bool,
+    pub history: History,
     pub state: S,
 }
 
@@ -107,53 +556,160 @@ impl<S> App<S> {
     pub fn new(state: S, window_params: WindowParameters, physics_dimensions: Vec2f) -> Self {
         set_window_size(window_params.width, window_params.height);
 
+        let app_context = AppContext {
+            materials: MaterialLibrary::load(MATERIALS_PATH).unwrap_or_else(|e| {
+                log::warn!("Could not load materials, using defaults: {}", e);
+                MaterialLibrary::defaults()
+            }),
+            ..AppContext::init(physics_dimensions)
+        };
+        Self::from_context(state, app_context)
+    }
+
+    // An app around an existing context. Opens no window, so it can be stepped in tests
+    pub fn from_context(state: S, app_context: AppContext) -> Self {
         Self {
-            app_context: AppContext {
-                entity_manager: EntityManager::init(),
-                ui_wants_keyboard: false,
-                ui_wants_pointer: false,
-                side_panel_left_rect: Rect {
-                    min: Pos2::new(0., 0.),
-                    max: Pos2::new(0., 0.),
-                },
-                ppu: 100.,
-                debug_outlines: false,
-                physics_dimensions,
-                current_shape: Shape::Circle,
-                show_forces: false,
-                show_com: false,
-            },
+            app_context,
+            input_source: Box::new(MacroquadInput),
             physics_engine: PhysicsEngine::init(),
-            systems: vec![],
+            schedule: Schedule::init(),
             paused: false,
+            history: History::init(),
             state,
         }
     }
 
-    pub fn add_system_function(&mut self, function: fn(&mut AppContext, f32, &mut S)) {
-        self.systems.push(function);
+    // Runs every frame before the physics update, named after the function
+    pub fn add_system_function(
+        &mut self,
+        function: impl FnMut(&mut AppContext, f32, &mut S) + 'static,
+    ) -> SystemId {
+        let name = std::any::type_name_of_val(&function);
+        self.schedule.add(Stage::PrePhysics, name, function)
+    }
+
+    pub fn add_system(
+        &mut self,
+        stage: Stage,
+        name: &str,
+        function: impl FnMut(&mut AppContext, f32, &mut S) + 'static,
+    ) -> SystemId {
+        self.schedule.add(stage, name, function)
+    }
+
+    // Advances the world by one update of `dt` seconds and records it
+    pub fn step(&mut self, dt: f32) {
+        self.schedule.run(
+            Stage::FixedUpdate,
+            &mut self.app_context,
+            dt,
+            &mut self.state,
+        );
+        self.history.resume();
+        self.physics_engine.update(&mut self.app_context, dt);
+        Scheduler::run(&mut self.app_context);
+        if self.history.is_due(self.physics_engine.steps()) {
+            let snapshot = self.physics_engine.snapshot(&self.app_context);
+            self.history.push(snapshot);
+        }
     }
 
     pub async fn run(&mut self) {
+        self.schedule
+            .run(Stage::Startup, &mut self.app_context, 0., &mut self.state);
         loop {
-            let dt = macroquad::time::get_frame_time();
-            for i in 0..self.systems.len() {
-                let system = self.systems[i];
-                (system)(&mut self.app_context, dt, &mut self.state);
-            }
+            // Wall clock seconds since the last frame, whatever the time scale or pause
+            let frame_time = get_frame_time();
+            self.app_context.input.update(self.input_source.as_ref());
+            self.app_context.update_viewport();
+            self.app_context.drag_force_fields();
+            self.app_context.drag_bodies(self.paused);
+            self.app_context.control_camera();
+            self.schedule.run(
+                Stage::PrePhysics,
+                &mut self.app_context,
+                frame_time,
+                &mut self.state,
+            );
+            // Every system has seen last step's events
+            self.app_context.events.clear();
 
             UiManager::render_ui(self);
 
-            clear_background(Color::from_hex(0x252526));
+            // Predicted from the world the systems saw, before it is stepped
+            let trajectories = std::mem::take(&mut self.app_context.trajectory_previews)
+                .into_iter()
+                .map(|(entity, steps)| {
+                    let color = entity.render.as_ref().map_or(WHITE, |render| render.color);
+                    let path = self
+                        .physics_engine
+                        .predict(&self.app_context, entity, steps);
+                    (path, color)
+                })
+                .collect::<Vec<_>>();
+
+            // Letterbox around the world
+            clear_background(Color::from_hex(0x1b1b1c));
+            // Simulated seconds that passed this frame
+            let mut simulated = 0.;
             if !self.paused {
-                self.physics_engine.update(&mut self.app_context);
+                let (steps, dt) = self.physics_engine.frame_steps(frame_time);
+                for _ in 0..steps {
+                    self.step(dt);
+                }
+                simulated = steps as f32 * dt;
+            } else if self.app_context.pressed(KeyCode::Period) {
+                simulated = self.physics_engine.fixed_dt;
+                self.step(simulated);
             }
+            self.schedule.run(
+                Stage::PostPhysics,
+                &mut self.app_context,
+                simulated,
+                &mut self.state,
+            );
+            self.app_context.follow_camera();
+            let camera = &self.app_context.camera;
+            let top_left = camera.to_screen(Vec2f::new(0., camera.world.y));
+            let size = camera.world * camera.scale();
+            draw_rectangle(
+                top_left.x,
+                top_left.y,
+                size.x,
+                size.y,
+                Color::from_hex(0x252526),
+            );
+            self.schedule.run(
+                Stage::PreRender,
+                &mut self.app_context,
+                frame_time,
+                &mut self.state,
+            );
             self.app_context.entity_manager.render_all(
-                self.app_context.physics_dimensions,
+                &self.app_context.camera,
                 self.app_context.debug_outlines,
                 self.app_context.show_forces,
                 self.app_context.show_com,
             );
+            for (path, color) in &trajectories {
+                draw_trajectory(path, &self.app_context.camera, *color);
+            }
+            self.app_context.entity_manager.render_force_fields(
+                &self.app_context.camera,
+                self.app_context.show_forces,
+                self.physics_engine.time(),
+            );
+            self.app_context.entity_manager.render_electric_field(
+                &self.app_context.camera,
+                self.app_context.show_electric_field,
+                &self.physics_engine.electrostatics,
+            );
+            self.schedule.run(
+                Stage::PostRender,
+                &mut self.app_context,
+                frame_time,
+                &mut self.state,
+            );
 
             egui_macroquad::draw();
             next_frame().await;
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+
+use macroquad::{
+    input::{
+        KeyCode, MouseButton, get_keys_down, is_mouse_button_down, mouse_position, mouse_wheel,
+    },
+    window::{screen_height, screen_width},
+};
+
+// A key or mouse button
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl From<KeyCode> for Button {
+    fn from(key: KeyCode) -> Self {
+        Button::Key(key)
+    }
+}
+
+impl From<MouseButton> for Button {
+    fn from(mouse_button: MouseButton) -> Self {
+        Button::Mouse(mouse_button)
+    }
+}
+
+// Where Input reads the state of the devices from
+pub trait InputSource {
+    // Every button held right now
+    fn buttons_down(&self) -> HashSet<Button>;
+    // Pixels, y down
+    fn mouse_position(&self) -> (f32, f32);
+    // How far the wheel turned since the last frame
+    fn mouse_wheel(&self) -> f32;
+    fn screen_size(&self) -> (f32, f32);
+}
+
+// The real devices, through macroquad
+pub struct MacroquadInput;
+
+impl InputSource for MacroquadInput {
+    fn buttons_down(&self) -> HashSet<Button> {
+        let mouse_buttons = [MouseButton::Left, MouseButton::Middle, MouseButton::Right]
+            .into_iter()
+            .filter(|mouse_button| is_mouse_button_down(*mouse_button))
+            .map(Button::Mouse);
+        get_keys_down()
+            .into_iter()
+            .map(Button::Key)
+            .chain(mouse_buttons)
+            .collect()
+    }
+
+    fn mouse_position(&self) -> (f32, f32) {
+        mouse_position()
+    }
+
+    fn mouse_wheel(&self) -> f32 {
+        mouse_wheel().1
+    }
+
+    fn screen_size(&self) -> (f32, f32) {
+        (screen_width(), screen_height())
+    }
+}
+
+// Scripted input for driving systems without a window
+#[derive(Clone, Debug)]
+pub struct FakeInput {
+    pub down: HashSet<Button>,
+    pub mouse_position: (f32, f32),
+    pub mouse_wheel: f32,
+    pub screen_size: (f32, f32),
+}
+
+impl FakeInput {
+    pub fn init() -> Self {
+        Self {
+            down: HashSet::new(),
+            mouse_position: (0., 0.),
+            mouse_wheel: 0.,
+            screen_size: (1920., 1080.),
+        }
+    }
+
+    pub fn press(&mut self, button: impl Into<Button>) {
+        self.down.insert(button.into());
+    }
+
+    pub fn release(&mut self, button: impl Into<Button>) {
+        self.down.remove(&button.into());
+    }
+}
+
+impl InputSource for FakeInput {
+    fn buttons_down(&self) -> HashSet<Button> {
+        self.down.clone()
+    }
+
+    fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_position
+    }
+
+    fn mouse_wheel(&self) -> f32 {
+        self.mouse_wheel
+    }
+
+    fn screen_size(&self) -> (f32, f32) {
+        self.screen_size
+    }
+}
+
+// The state of the devices this frame and the last, so presses and releases are only seen on
+// the frame they happen. Actions are names bound to any number of buttons, AppContext answers
+// for them
+pub struct Input {
+    down: HashSet<Button>,
+    previous: HashSet<Button>,
+    previous_mouse_position: (f32, f32),
+    pub mouse_position: (f32, f32),
+    pub mouse_wheel: f32,
+    pub screen_size: (f32, f32),
+    pub actions: HashMap<String, Vec<Button>>,
+}
+
+impl Input {
+    pub fn init() -> Self {
+        Self {
+            down: HashSet::new(),
+            previous: HashSet::new(),
+            previous_mouse_position: (0., 0.),
+            mouse_position: (0., 0.),
+            mouse_wheel: 0.,
+            screen_size: (1., 1.),
+            actions: HashMap::new(),
+        }
+    }
+
+    // Reads the next frame from `source`
+    pub fn update(&mut self, source: &dyn InputSource) {
+        self.previous = std::mem::replace(&mut self.down, source.buttons_down());
+        self.previous_mouse_position = self.mouse_position;
+        self.mouse_position = source.mouse_position();
+        self.mouse_wheel = source.mouse_wheel();
+        self.screen_size = source.screen_size();
+    }
+
+    // How far the mouse moved since the last frame, in pixels
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        (
+            self.mouse_position.0 - self.previous_mouse_position.0,
+            self.mouse_position.1 - self.previous_mouse_position.1,
+        )
+    }
+
+    // Went down this frame
+    pub fn pressed(&self, button: impl Into<Button>) -> bool {
+        let button = button.into();
+        self.down.contains(&button) && !self.previous.contains(&button)
+    }
+
+    // Came up this frame
+    pub fn released(&self, button: impl Into<Button>) -> bool {
+        let button = button.into();
+        !self.down.contains(&button) && self.previous.contains(&button)
+    }
+
+    pub fn held(&self, button: impl Into<Button>) -> bool {
+        self.down.contains(&button.into())
+    }
+
+    pub fn bind(&mut self, action: &str, button: impl Into<Button>) {
+        self.actions
+            .entry(action.to_owned())
+            .or_default()
+            .push(button.into());
+    }
+
+    pub fn unbind(&mut self, action: &str) -> Option<Vec<Button>> {
+        self.actions.remove(action)
+    }
+
+    // Buttons bound to the action, empty for unknown actions
+    pub fn bindings(&self, action: &str) -> &[Button] {
+        self.actions.get(action).map_or(&[], |buttons| buttons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::input::{KeyCode, MouseButton};
+
+    use super::{FakeInput, Input};
+    use crate::{app::AppContext, math::math::Vec2f};
+
+    #[test]
+    fn presses_and_releases_last_one_frame() {
+        let mut source = FakeInput::init();
+        let mut input = Input::init();
+
+        source.press(KeyCode::Space);
+        input.update(&source);
+        assert!(input.pressed(KeyCode::Space));
+        assert!(input.held(KeyCode::Space));
+        assert!(!input.released(KeyCode::Space));
+
+        input.update(&source);
+        assert!(!input.pressed(KeyCode::Space));
+        assert!(input.held(KeyCode::Space));
+
+        source.release(KeyCode::Space);
+        input.update(&source);
+        assert!(input.released(KeyCode::Space));
+        assert!(!input.held(KeyCode::Space));
+
+        input.update(&source);
+        assert!(!input.released(KeyCode::Space));
+    }
+
+    #[test]
+    fn mouse_delta_is_per_frame() {
+        let mut source = FakeInput::init();
+        let mut input = Input::init();
+        source.mouse_position = (10., 20.);
+        input.update(&source);
+        source.mouse_position = (15., 18.);
+        input.update(&source);
+        assert_eq!(input.mouse_delta(), (5., -2.));
+        input.update(&source);
+        assert_eq!(input.mouse_delta(), (0., 0.));
+    }
+
+    #[test]
+    fn actions_follow_any_bound_button() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        app_context.input.bind("jump", KeyCode::Space);
+        app_context.input.bind("jump", MouseButton::Left);
+        let mut source = FakeInput::init();
+        // Away from the side panel, which would capture the mouse
+        source.mouse_position = (500., 500.);
+
+        source.press(KeyCode::Space);
+        app_context.input.update(&source);
+        assert!(app_context.action_pressed("jump"));
+        assert!(app_context.action_held("jump"));
+
+        source.press(MouseButton::Left);
+        app_context.input.update(&source);
+        assert!(app_context.action_held("jump"));
+
+        // Still held through the mouse button
+        source.release(KeyCode::Space);
+        app_context.input.update(&source);
+        assert!(app_context.action_held("jump"));
+        assert!(!app_context.action_released("jump"));
+
+        source.release(MouseButton::Left);
+        app_context.input.update(&source);
+        assert!(!app_context.action_held("jump"));
+        assert!(app_context.action_released("jump"));
+
+        assert!(app_context.input.unbind("jump").is_some());
+        assert!(app_context.input.bindings("jump").is_empty());
+    }
+
+    #[test]
+    fn ui_captures_the_mouse_but_not_releases() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let mut source = FakeInput::init();
+        source.mouse_position = (500., 500.);
+        source.press(MouseButton::Left);
+        app_context.input.update(&source);
+        assert!(app_context.pressed(MouseButton::Left));
+
+        app_context.ui_wants_pointer = true;
+        assert!(!app_context.held(MouseButton::Left));
+        source.release(MouseButton::Left);
+        app_context.input.update(&source);
+        assert!(app_context.released(MouseButton::Left));
+    }
+}
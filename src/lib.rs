@@ -1,4 +1,7 @@
 pub mod app;
+pub mod input;
 pub mod math;
 pub mod physics;
 pub mod renderer;
+pub mod schedule;
+pub mod timer;
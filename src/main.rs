@@ -1,34 +1,45 @@
-use macroquad::{color::WHITE, input::MouseButton};
+use macroquad::{color::WHITE, input::MouseButton, time::get_time};
 use physics_sim::app::{App, AppContext, WindowParameters};
 use physics_sim::math::math::Vec2f;
-use physics_sim::physics::entities::physics_body::RigidBody;
-use physics_sim::renderer::entity::{EntityId, Shape};
-
-fn spawn_ball_onclick(app_context: &mut AppContext, dt: f32, state: &mut AppState) {
-    if state.new_timer <= 0. {
-        state.clicked = false;
-        state.new_timer = 0.2
-    } else if state.clicked {
-        state.new_timer -= dt;
-        return;
-    }
-    if app_context.get_button_press(MouseButton::Left) {
-        state.clicked = true;
+use physics_sim::physics::entities::physics_body::{PhysicsBody, RigidBody};
+use physics_sim::renderer::entity::Shape;
+use physics_sim::timer::Timer;
+
+// Holding the button spawns a ball every 0.2 seconds. The cooldown runs on the wall clock,
+// so spawning also works while the simulation is paused
+fn spawn_ball_onclick(app_context: &mut AppContext, _dt: f32, state: &mut AppState) {
+    if app_context.action_held("spawn") && state.spawn_cooldown.trigger(get_time() as f32) {
         let mouse_pos = app_context.get_mouse_position();
-        app_context.new_entity(
-            mouse_pos,
-            0.2,
-            Vec2f::new(0.2, 0.2),
-            WHITE,
-            RigidBody::Dynamic,
-        );
+        app_context.new_entity(mouse_pos, Vec2f::new(0.2, 0.2), WHITE, RigidBody::Dynamic);
+    }
+}
+
+// Drag with the right button to aim, the ball is launched on release
+fn launch_ball_ondrag(app_context: &mut AppContext, _dt: f32, state: &mut AppState) {
+    let mouse_pos = app_context.get_mouse_position();
+    let size = Vec2f::new(0.2, 0.2);
+    if app_context.action_held("aim") {
+        let start = *state.aim_start.get_or_insert(mouse_pos);
+        let velocity = (start - mouse_pos) * LAUNCH_SPEED;
+        app_context.preview_launch(start, size, WHITE, velocity, 120);
+    } else if let Some(start) = state.aim_start.take() {
+        let ball = app_context.new_entity(start, size, WHITE, RigidBody::Dynamic);
+        let body = app_context
+            .entity_manager
+            .components
+            .get_mut::<PhysicsBody>(&ball);
+        if let Some(body) = body {
+            body.velocity = (start - mouse_pos) * LAUNCH_SPEED;
+        }
     }
 }
 
+// Launch speed in m/s per meter dragged
+const LAUNCH_SPEED: f32 = 4.;
+
 pub struct AppState {
-    pub balls: Vec<EntityId>,
-    pub new_timer: f32,
-    pub clicked: bool,
+    pub spawn_cooldown: Timer,
+    pub aim_start: Option<Vec2f>,
 }
 
 #[macroquad::main("Physics Simulator")]
@@ -37,9 +48,8 @@ async fn main() {
 
     let mut app = App::new(
         AppState {
-            balls: vec![],
-            new_timer: 0.2,
-            clicked: false,
+            spawn_cooldown: Timer::cooldown(0.2),
+            aim_start: None,
         },
         WindowParameters {
             width: 1920,
@@ -51,13 +61,15 @@ async fn main() {
     // Base plate
     app.app_context.new_entity_shaped(
         Vec2f::new(7.5, 0.5),
-        0.,
         Vec2f::new(15., 1.),
         WHITE,
         Shape::Rectangle,
         RigidBody::Static,
     );
 
+    app.app_context.input.bind("spawn", MouseButton::Left);
+    app.app_context.input.bind("aim", MouseButton::Right);
     app.add_system_function(spawn_ball_onclick);
+    app.add_system_function(launch_ball_ondrag);
     app.run().await;
 }
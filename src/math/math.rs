@@ -3,7 +3,7 @@ pub struct Vec2u {
     pub y: u32,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec2f {
     pub x: f32,
     pub y: f32,
@@ -77,8 +77,8 @@ impl std::ops::Sub for Vec2f {
     type Output = Vec2f;
     fn sub(self, rhs: Self) -> Vec2f {
         Vec2f {
-            x: rhs.x - self.x,
-            y: rhs.y - self.y,
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
         }
     }
 }
@@ -113,6 +113,24 @@ impl Vec2f {
         self.x * rhs.x + self.y * rhs.y
     }
 
+    // z component of the 3D cross product
+    pub fn cross(&self, rhs: &Vec2f) -> f32 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    // Counter-clockwise rotation by `angle` radians
+    pub fn rotate(&self, angle: f32) -> Vec2f {
+        let (sin, cos) = angle.sin_cos();
+        Vec2f {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+
     pub fn clamp(self, min: f32, max: f32) -> Vec2f {
         Vec2f {
             x: self.x.clamp(min, max),
@@ -134,3 +152,35 @@ pub struct Vec2i {
     pub x: i32,
     pub y: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Vec2f;
+
+    fn xy(v: Vec2f) -> (f32, f32) {
+        (v.x, v.y)
+    }
+
+    #[test]
+    fn subtraction_is_left_minus_right() {
+        let a = Vec2f::new(5., 3.);
+        let b = Vec2f::new(1., 4.);
+        assert_eq!(xy(a - b), (4., -1.));
+        assert_eq!(xy(b - a), (-4., 1.));
+
+        let mut c = a;
+        c -= b;
+        assert_eq!(xy(c), xy(a - b));
+        // Matches adding the negation
+        assert_eq!(xy(a - b), xy(a + -b));
+    }
+
+    // The old order gave rhs - self, so the collision response's `v_b - b_invm * j * n`
+    // turned b's velocity around even when the impulse was zero
+    #[test]
+    fn subtracting_nothing_keeps_the_vector() {
+        let velocity = Vec2f::new(2., -3.);
+        assert_eq!(xy(velocity - Vec2f::new(0., 0.)), (2., -3.));
+        assert_eq!(xy(velocity - 0. * Vec2f::new(1., 1.)), (2., -3.));
+    }
+}
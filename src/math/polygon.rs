@@ -0,0 +1,411 @@
+use crate::math::math::Vec2f;
+
+// Signed area by the shoelace formula, positive for counter-clockwise vertices
+pub fn signed_area(vertices: &[Vec2f]) -> f32 {
+    let mut area = 0.;
+    for (i, v) in vertices.iter().enumerate() {
+        let next = vertices[(i + 1) % vertices.len()];
+        area += v.cross(&next);
+    }
+    area / 2.
+}
+
+pub fn area(vertices: &[Vec2f]) -> f32 {
+    signed_area(vertices).abs()
+}
+
+pub fn centroid(vertices: &[Vec2f]) -> Vec2f {
+    let area = signed_area(vertices);
+    if area.abs() < 1e-9 {
+        // Degenerate, fall back to the vertex average
+        let sum = vertices.iter().fold(Vec2f::zero(), |sum, v| sum + *v);
+        return sum / vertices.len() as f32;
+    }
+
+    let mut c = Vec2f::zero();
+    for (i, v) in vertices.iter().enumerate() {
+        let next = vertices[(i + 1) % vertices.len()];
+        c += (*v + next) * v.cross(&next);
+    }
+    c / (6. * area)
+}
+
+// Moment of inertia around the origin for a polygon of uniform density
+pub fn inertia(vertices: &[Vec2f], mass: f32) -> f32 {
+    let mut numerator = 0.;
+    let mut denominator = 0.;
+    for (i, v) in vertices.iter().enumerate() {
+        let next = vertices[(i + 1) % vertices.len()];
+        let cross = v.cross(&next);
+        numerator += cross * (v.dot(v) + v.dot(&next) + next.dot(&next));
+        denominator += cross;
+    }
+    if denominator.abs() < 1e-9 {
+        return 0.;
+    }
+    (mass / 6. * numerator / denominator).abs()
+}
+
+// Checks the vertices make a polygon the collision code can use: at least three corners,
+// some area, no crossing edges and convex, in either winding order
+pub fn validate(vertices: &[Vec2f]) -> Result<(), String> {
+    if vertices.len() < 3 {
+        return Err(format!(
+            "A polygon needs at least 3 vertices, got {}",
+            vertices.len()
+        ));
+    }
+    if vertices
+        .iter()
+        .any(|v| !v.x.is_finite() || !v.y.is_finite())
+    {
+        return Err("Polygon vertices must be finite".to_string());
+    }
+    let area = signed_area(vertices);
+    if area.abs() < 1e-9 {
+        return Err("Polygon has no area".to_string());
+    }
+    // A star turns the same way at every corner, but its edges cross
+    if self_intersects(vertices) {
+        return Err("Polygon intersects itself".to_string());
+    }
+    let mut ordered = vertices.to_vec();
+    if area < 0. {
+        ordered.reverse();
+    }
+    if !is_convex(&ordered) {
+        return Err("Polygon is not convex".to_string());
+    }
+    Ok(())
+}
+
+// Checks the points make a chain the collision code can use: at least two points, all
+// finite, and no segment of zero length
+pub fn validate_chain(vertices: &[Vec2f]) -> Result<(), String> {
+    if vertices.len() < 2 {
+        return Err(format!(
+            "A chain needs at least 2 points, got {}",
+            vertices.len()
+        ));
+    }
+    if vertices
+        .iter()
+        .any(|v| !v.x.is_finite() || !v.y.is_finite())
+    {
+        return Err("Chain points must be finite".to_string());
+    }
+    if let Some(i) = (1..vertices.len()).find(|&i| (vertices[i] - vertices[i - 1]).length() < 1e-6)
+    {
+        return Err(format!("Chain segment {} has no length", i - 1));
+    }
+    Ok(())
+}
+
+// Orders the vertices counter-clockwise, so the edge normals point out
+pub fn orient(vertices: &mut [Vec2f]) {
+    if signed_area(vertices) < 0. {
+        vertices.reverse();
+    }
+}
+
+// Moves the centroid to the origin and orders the vertices counter-clockwise.
+// Returns the centroid that was subtracted
+pub fn normalize(vertices: &mut [Vec2f]) -> Vec2f {
+    orient(vertices);
+    let c = centroid(vertices);
+    for v in vertices.iter_mut() {
+        *v -= c;
+    }
+    c
+}
+
+// Outward normals, one per edge from vertex i to i + 1
+pub fn normals(vertices: &[Vec2f]) -> Vec<Vec2f> {
+    (0..vertices.len())
+        .map(|i| {
+            let edge = vertices[(i + 1) % vertices.len()] - vertices[i];
+            Vec2f::new(edge.y, -edge.x).norm()
+        })
+        .collect()
+}
+
+// Regular polygon around the origin with a vertex pointing up
+pub fn regular(sides: usize, radius: f32) -> Vec<Vec2f> {
+    (0..sides)
+        .map(|i| {
+            let angle =
+                std::f32::consts::FRAC_PI_2 + std::f32::consts::TAU * i as f32 / sides as f32;
+            Vec2f::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+fn is_convex_corner(prev: Vec2f, v: Vec2f, next: Vec2f) -> bool {
+    (v - prev).cross(&(next - v)) > 1e-9
+}
+
+fn in_triangle(p: Vec2f, a: Vec2f, b: Vec2f, c: Vec2f) -> bool {
+    (b - a).cross(&(p - a)) >= 0. && (c - b).cross(&(p - b)) >= 0. && (a - c).cross(&(p - c)) >= 0.
+}
+
+fn is_convex(vertices: &[Vec2f]) -> bool {
+    (0..vertices.len()).all(|i| {
+        let prev = vertices[(i + vertices.len() - 1) % vertices.len()];
+        let next = vertices[(i + 1) % vertices.len()];
+        (vertices[i] - prev).cross(&(next - vertices[i])) >= -1e-9
+    })
+}
+
+// Whether segments ab and cd share a point
+fn segments_touch(a: Vec2f, b: Vec2f, c: Vec2f, d: Vec2f) -> bool {
+    let side = |p: Vec2f, q: Vec2f, r: Vec2f| {
+        let cross = (q - p).cross(&(r - p));
+        if cross.abs() < 1e-9 {
+            0.
+        } else {
+            cross.signum()
+        }
+    };
+    let (d1, d2) = (side(a, b, c), side(a, b, d));
+    let (d3, d4) = (side(c, d, a), side(c, d, b));
+    if d1 == 0. && d2 == 0. {
+        // On one line, so they touch if their spans along it overlap
+        let axis = b - a;
+        let (t_c, t_d) = ((c - a).dot(&axis), (d - a).dot(&axis));
+        return t_c.max(t_d) >= 0. && t_c.min(t_d) <= axis.dot(&axis);
+    }
+    d1 * d2 <= 0. && d3 * d4 <= 0.
+}
+
+// Whether two edges that are not neighbours cross or touch
+fn self_intersects(outline: &[Vec2f]) -> bool {
+    let n = outline.len();
+    (0..n).any(|i| {
+        (i + 2..n).filter(|&j| (j + 1) % n != i).any(|j| {
+            segments_touch(
+                outline[i],
+                outline[(i + 1) % n],
+                outline[j],
+                outline[(j + 1) % n],
+            )
+        })
+    })
+}
+
+// Drops repeated vertices and ones in the middle of a straight edge. Vertices where the
+// outline doubles back are kept, so they still count as touching edges
+fn simplify(outline: &[Vec2f]) -> Vec<Vec2f> {
+    let mut outline = outline.to_vec();
+    // Removing one corner changes its neighbours, so go round until nothing changes
+    let mut changed = true;
+    while changed && outline.len() > 2 {
+        changed = false;
+        let n = outline.len();
+        let flat = (0..n).find(|&i| {
+            let (prev, v, next) = (outline[(i + n - 1) % n], outline[i], outline[(i + 1) % n]);
+            let (e1, e2) = (v - prev, next - v);
+            let repeated = e1.length() < 1e-6;
+            let straight =
+                e1.cross(&e2).abs() <= 1e-6 * e1.length() * e2.length() && e1.dot(&e2) > 0.;
+            repeated || straight
+        });
+        if let Some(i) = flat {
+            outline.remove(i);
+            changed = true;
+        }
+    }
+    outline
+}
+
+// Joins two counter-clockwise pieces along a shared edge, if they have one
+fn merge(p: &[usize], q: &[usize]) -> Option<Vec<usize>> {
+    for i in 0..p.len() {
+        let (a, b) = (p[i], p[(i + 1) % p.len()]);
+        let Some(j) = (0..q.len()).find(|&j| q[j] == b && q[(j + 1) % q.len()] == a) else {
+            continue;
+        };
+        // Walk p from b round to a, then q from after a round to before b
+        let mut merged = (0..p.len())
+            .map(|k| p[(i + 1 + k) % p.len()])
+            .collect::<Vec<usize>>();
+        merged.extend((2..q.len()).map(|k| q[(j + k) % q.len()]));
+        return Some(merged);
+    }
+    None
+}
+
+// Splits a simple polygon into convex pieces. The outline is cut into triangles by ear
+// clipping, then neighbouring pieces are merged for as long as the result stays convex
+pub fn decompose(outline: &[Vec2f]) -> Result<Vec<Vec<Vec2f>>, String> {
+    if outline.len() < 3 {
+        return Err(format!(
+            "An outline needs at least 3 vertices, got {}",
+            outline.len()
+        ));
+    }
+    // A corner that does not turn can never be an ear
+    let mut outline = simplify(outline);
+    if signed_area(&outline).abs() < 1e-9 {
+        return Err("Outline has no area".to_string());
+    }
+    if self_intersects(&outline) {
+        return Err("Outline intersects itself".to_string());
+    }
+    if signed_area(&outline) < 0. {
+        outline.reverse();
+    }
+    if is_convex(&outline) {
+        return Ok(vec![outline]);
+    }
+
+    let mut pieces: Vec<Vec<usize>> = vec![];
+    let mut remaining = (0..outline.len()).collect::<Vec<usize>>();
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (prev, v, next) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (a, b, c) = (outline[prev], outline[v], outline[next]);
+            is_convex_corner(a, b, c)
+                && remaining
+                    .iter()
+                    .filter(|&&k| k != prev && k != v && k != next)
+                    .all(|&k| !in_triangle(outline[k], a, b, c))
+        });
+        // Only self intersecting outlines run out of ears
+        let Some(i) = ear else {
+            return Err("Outline could not be split into convex pieces".to_string());
+        };
+        pieces.push(vec![
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ]);
+        remaining.remove(i);
+    }
+    pieces.push(remaining);
+
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'search: for i in 0..pieces.len() {
+            for j in i + 1..pieces.len() {
+                let Some(piece) = merge(&pieces[i], &pieces[j]) else {
+                    continue;
+                };
+                let vertices = piece.iter().map(|&k| outline[k]).collect::<Vec<Vec2f>>();
+                if is_convex(&vertices) {
+                    pieces[i] = piece;
+                    pieces.remove(j);
+                    merged = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+
+    Ok(pieces
+        .into_iter()
+        .map(|piece| piece.into_iter().map(|k| outline[k]).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(points: &[(f32, f32)]) -> Vec<Vec2f> {
+        points.iter().map(|&(x, y)| Vec2f::new(x, y)).collect()
+    }
+
+    // Every piece is a valid convex polygon and together they cover the outline
+    fn assert_decomposes(points: &[(f32, f32)]) -> usize {
+        let outline = outline(points);
+        let pieces = decompose(&outline).unwrap();
+        for piece in &pieces {
+            assert!(validate(piece).is_ok(), "{:?} is not convex", piece);
+        }
+        let total = pieces.iter().map(|piece| area(piece)).sum::<f32>();
+        assert!((total - area(&outline)).abs() < 1e-4);
+        pieces.len()
+    }
+
+    #[test]
+    fn l_bracket() {
+        let pieces =
+            assert_decomposes(&[(0., 0.), (3., 0.), (3., 1.), (1., 1.), (1., 3.), (0., 3.)]);
+        assert_eq!(pieces, 2);
+    }
+
+    #[test]
+    fn comb() {
+        // Three teeth pointing up, wound clockwise
+        let pieces = assert_decomposes(&[
+            (0., 0.),
+            (0., 3.),
+            (1., 3.),
+            (1., 1.),
+            (2., 1.),
+            (2., 3.),
+            (3., 3.),
+            (3., 1.),
+            (4., 1.),
+            (4., 3.),
+            (5., 3.),
+            (5., 0.),
+        ]);
+        assert!(pieces >= 3);
+    }
+
+    #[test]
+    fn already_convex() {
+        assert_eq!(
+            assert_decomposes(&[(0., 0.), (2., 0.), (2., 1.), (0., 1.)]),
+            1
+        );
+        assert_eq!(assert_decomposes(&[(0., 0.), (1., 0.), (0., 1.)]), 1);
+    }
+
+    #[test]
+    fn stars_are_not_convex() {
+        // Every other corner of a regular pentagon, so each corner turns the same way
+        let pentagon = regular(5, 1.);
+        let star = [0, 2, 4, 1, 3].map(|i| pentagon[i]);
+        assert!(signed_area(&star).abs() > 1e-3);
+        assert!(validate(&star).is_err());
+        assert!(validate(&pentagon).is_ok());
+    }
+
+    #[test]
+    fn straight_and_repeated_corners() {
+        // An L with a point halfway along the bottom, another on the inner edge and a repeat
+        let pieces = assert_decomposes(&[
+            (0., 0.),
+            (1.5, 0.),
+            (3., 0.),
+            (3., 1.),
+            (3., 1.),
+            (2., 1.),
+            (1., 1.),
+            (1., 3.),
+            (0., 3.),
+            (0., 0.),
+        ]);
+        assert_eq!(pieces, 2);
+    }
+
+    #[test]
+    fn invalid_outlines_are_refused() {
+        // A bow tie crosses itself in the middle
+        let bow_tie = outline(&[(0., 0.), (2., 2.), (2., 0.), (0., 2.)]);
+        assert!(decompose(&bow_tie).is_err());
+        // Two loops that touch at one corner
+        let figure_eight = outline(&[(0., 0.), (1., 1.), (2., 0.), (2., 2.), (1., 1.), (0., 2.)]);
+        assert!(decompose(&figure_eight).is_err());
+        assert!(decompose(&outline(&[(0., 0.), (1., 1.)])).is_err());
+        assert!(decompose(&outline(&[(0., 0.), (1., 1.), (2., 2.), (3., 3.)])).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    math::math::Vec2f,
+    physics::entities::physics_body::PhysicsBody,
+    renderer::component::{ColliderComponent, TransformComponent},
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BoundaryMode {
+    // Bodies can leave the physics rectangle
+    None,
+    // Bodies bounce off the edges of the physics rectangle
+    Walls,
+    // Bodies leaving one edge come back in through the opposite one
+    Periodic,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Boundary {
+    pub mode: BoundaryMode,
+    // Coefficient of restitution of the walls
+    pub restitution: f32,
+}
+
+impl Boundary {
+    pub fn init() -> Self {
+        Self {
+            mode: BoundaryMode::None,
+            restitution: 0.5,
+        }
+    }
+
+    // Shortest vector from `from` to `to`. In periodic mode this may cross a seam
+    pub fn delta(&self, from: Vec2f, to: Vec2f, dimensions: Vec2f) -> Vec2f {
+        let mut delta = to - from;
+        if self.mode == BoundaryMode::Periodic {
+            delta.x -= dimensions.x * (delta.x / dimensions.x).round();
+            delta.y -= dimensions.y * (delta.y / dimensions.y).round();
+        }
+        delta
+    }
+
+    // Keeps a dynamic body inside the world after it has moved. Bodies without a collider
+    // are kept in by their center
+    pub fn apply(
+        &self,
+        transform: &mut TransformComponent,
+        body: &mut PhysicsBody,
+        collider: Option<&ColliderComponent>,
+        dimensions: Vec2f,
+    ) {
+        if !body.is_dynamic() {
+            return;
+        }
+
+        let position = &mut transform.position;
+        match self.mode {
+            BoundaryMode::None => {}
+            BoundaryMode::Walls => {
+                // Push the shape's bounds back inside and reflect the velocity
+                let (min, max) = collider.map_or((Vec2f::zero(), Vec2f::zero()), |collider| {
+                    collider.shape.bounds(collider.size, transform.rotation)
+                });
+                let (left, right) = (position.x + min.x, position.x + max.x);
+                let (bottom, top) = (position.y + min.y, position.y + max.y);
+                if left < 0. {
+                    position.x -= left;
+                    body.velocity.x = body.velocity.x.abs() * self.restitution;
+                } else if right > dimensions.x {
+                    position.x -= right - dimensions.x;
+                    body.velocity.x = -body.velocity.x.abs() * self.restitution;
+                }
+                if bottom < 0. {
+                    position.y -= bottom;
+                    body.velocity.y = body.velocity.y.abs() * self.restitution;
+                } else if top > dimensions.y {
+                    position.y -= top - dimensions.y;
+                    body.velocity.y = -body.velocity.y.abs() * self.restitution;
+                }
+            }
+            BoundaryMode::Periodic => {
+                position.x = position.x.rem_euclid(dimensions.x);
+                position.y = position.y.rem_euclid(dimensions.y);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    physics::{
+        collisions::solver::{Contact, SolverBody},
+        entities::physics_body::PhysicsBody,
+        joint::{Joint, JointId},
+    },
+    renderer::{
+        component::{ColliderComponent, ComponentManager, TransformComponent},
+        entity::EntityId,
+    },
+};
+
+// A group of bodies connected through contacts and joints. Islands never share a dynamic
+// body, so each one can be solved on its own thread. Static bodies are copied into every
+// island that touches them and are never written back.
+pub struct Island {
+    pub contacts: Vec<Contact>,
+    pub joints: Vec<(JointId, Joint)>,
+    pub bodies: HashMap<EntityId, SolverBody>,
+}
+
+fn is_dynamic(components: &ComponentManager, id: &EntityId) -> bool {
+    components
+        .get::<PhysicsBody>(id)
+        .is_some_and(|body| body.is_dynamic())
+}
+
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+pub fn build_islands(
+    contacts: Vec<Contact>,
+    joints: &BTreeMap<JointId, Joint>,
+    components: &ComponentManager,
+    deterministic: bool,
+) -> Vec<Island> {
+    // Joints need both ends placed in the world, the others are left alone
+    let placed = |id| components.get::<TransformComponent>(id).is_some();
+    let joints = joints
+        .iter()
+        .filter(|(_, joint)| placed(&joint.a) && placed(&joint.b))
+        .collect::<Vec<_>>();
+    let links = contacts
+        .iter()
+        .map(|contact| (contact.a, contact.b))
+        .chain(joints.iter().map(|(_, joint)| (joint.a, joint.b)))
+        .collect::<Vec<(EntityId, EntityId)>>();
+
+    // Union-find over the dynamic bodies taking part in a contact or joint
+    let mut indices: HashMap<EntityId, usize> = HashMap::new();
+    let mut parents: Vec<usize> = vec![];
+    for (a, b) in &links {
+        for id in [a, b] {
+            if is_dynamic(components, id) && !indices.contains_key(id) {
+                indices.insert(*id, parents.len());
+                parents.push(parents.len());
+            }
+        }
+    }
+
+    for (a, b) in &links {
+        if let (Some(&i_a), Some(&i_b)) = (indices.get(a), indices.get(b)) {
+            let root_a = find(&mut parents, i_a);
+            let root_b = find(&mut parents, i_b);
+            parents[root_a] = root_b;
+        }
+    }
+
+    let mut islands: Vec<Island> = vec![];
+    let mut island_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut island_of = |a: EntityId, b: EntityId, islands: &mut Vec<Island>| {
+        // Static against static has nothing to solve
+        let index = *indices.get(&a).or(indices.get(&b))?;
+        let root = find(&mut parents, index);
+        let island_index = *island_of_root.entry(root).or_insert_with(|| {
+            islands.push(Island {
+                contacts: vec![],
+                joints: vec![],
+                bodies: HashMap::new(),
+            });
+            islands.len() - 1
+        });
+
+        let island = &mut islands[island_index];
+        for id in [a, b] {
+            island.bodies.entry(id).or_insert_with(|| {
+                let transform = components.get::<TransformComponent>(&id).unwrap();
+                SolverBody::new(transform, components.get::<PhysicsBody>(&id))
+            });
+        }
+        Some(island_index)
+    };
+    for contact in contacts {
+        if let Some(index) = island_of(contact.a, contact.b, &mut islands) {
+            islands[index].contacts.push(contact);
+        }
+    }
+    for (id, joint) in joints {
+        if let Some(index) = island_of(joint.a, joint.b, &mut islands) {
+            islands[index].joints.push((*id, joint.clone()));
+        }
+    }
+
+    if deterministic {
+        // Solve contacts in id order instead of broad phase order
+        for island in &mut islands {
+            island
+                .contacts
+                .sort_by_key(|contact| (contact.a, contact.b));
+        }
+    }
+
+    islands
+}
+
+// Copies the solved bodies back and hands out the joints with their new reaction forces
+pub fn write_back(
+    islands: Vec<Island>,
+    components: &mut ComponentManager,
+) -> Vec<(JointId, Joint)> {
+    let mut joints = vec![];
+    for island in islands {
+        for (id, solved) in island.bodies {
+            if !is_dynamic(components, &id) {
+                continue;
+            }
+            let body = components.get_mut::<PhysicsBody>(&id).unwrap();
+            body.velocity = solved.velocity;
+            body.angular_velocity = solved.angular_velocity;
+            let transform = components.get_mut::<TransformComponent>(&id).unwrap();
+            transform.position = solved.position;
+            transform.rotation = solved.rotation;
+            let transform = *transform;
+            if let Some(collider) = components.get_mut::<ColliderComponent>(&id) {
+                collider.update_bounding_box(&transform);
+            }
+        }
+        joints.extend(island.joints);
+    }
+    joints
+}
@@ -0,0 +1,3 @@
+pub mod island;
+pub mod sat;
+pub mod solver;
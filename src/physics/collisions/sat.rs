@@ -0,0 +1,669 @@
+use crate::{
+    math::{math::Vec2f, polygon},
+    renderer::{component::ColliderComponent, entity::Shape},
+};
+
+// A shape placed in the world
+pub enum Collider {
+    Circle {
+        center: Vec2f,
+        radius: f32,
+    },
+    // Convex, counter-clockwise
+    Polygon {
+        vertices: Vec<Vec2f>,
+        normals: Vec<Vec2f>,
+    },
+    // Segment from `a` to `b` swept by a circle
+    Capsule {
+        a: Vec2f,
+        b: Vec2f,
+        radius: f32,
+    },
+    // One piece of a chain, knowing its neighbours so it can skip ghost contacts
+    Segment {
+        a: Vec2f,
+        b: Vec2f,
+        prev: Option<Vec2f>,
+        next: Option<Vec2f>,
+        one_sided: bool,
+    },
+}
+
+impl Collider {
+    // The collider's shapes placed at `position`, one per part of a compound
+    pub fn from_component(
+        collider: &ColliderComponent,
+        position: Vec2f,
+        rotation: f32,
+    ) -> Vec<Self> {
+        let mut colliders = vec![];
+        Self::from_shape(
+            &collider.shape,
+            collider.size,
+            position,
+            rotation,
+            &mut colliders,
+        );
+        colliders
+    }
+
+    fn from_shape(
+        shape: &Shape,
+        size: Vec2f,
+        position: Vec2f,
+        rotation: f32,
+        colliders: &mut Vec<Self>,
+    ) {
+        let vertices = shape
+            .vertices(size)
+            .iter()
+            .map(|v| position + v.rotate(rotation))
+            .collect::<Vec<Vec2f>>();
+        match shape {
+            Shape::Circle => colliders.push(Collider::Circle {
+                center: position,
+                radius: size.x,
+            }),
+            Shape::Capsule { radius, .. } => colliders.push(Collider::Capsule {
+                a: vertices[0],
+                b: vertices[1],
+                radius: *radius,
+            }),
+            Shape::Chain { one_sided, .. } => {
+                for i in 0..vertices.len().saturating_sub(1) {
+                    colliders.push(Collider::Segment {
+                        a: vertices[i],
+                        b: vertices[i + 1],
+                        prev: i.checked_sub(1).map(|j| vertices[j]),
+                        next: vertices.get(i + 2).copied(),
+                        one_sided: *one_sided,
+                    });
+                }
+            }
+            Shape::Compound(parts) => {
+                for part in parts {
+                    let offset = part.offset.rotate(rotation);
+                    Self::from_shape(
+                        &part.shape,
+                        part.size,
+                        position + offset,
+                        rotation,
+                        colliders,
+                    );
+                }
+            }
+            _ => colliders.push(Collider::Polygon {
+                normals: polygon::normals(&vertices),
+                vertices,
+            }),
+        }
+    }
+
+    // Whether `point` is inside the shape. Chain segments have no inside
+    pub fn contains(&self, point: Vec2f) -> bool {
+        match self {
+            Collider::Circle { center, radius } => (point - *center).length() <= *radius,
+            Collider::Polygon { vertices, normals } => vertices
+                .iter()
+                .zip(normals)
+                .all(|(vertex, normal)| normal.dot(&(point - *vertex)) <= 0.),
+            Collider::Capsule { a, b, radius } => {
+                (point - closest_on_segment(*a, *b, point)).length() <= *radius
+            }
+            Collider::Segment { .. } => false,
+        }
+    }
+
+    // Polygons and capsules as a core polygon with a rounding radius
+    fn rounded(&self) -> Option<(Vec<Vec2f>, Vec<Vec2f>, f32)> {
+        match self {
+            Collider::Circle { .. } | Collider::Segment { .. } => None,
+            Collider::Polygon { vertices, normals } => {
+                Some((vertices.clone(), normals.clone(), 0.))
+            }
+            Collider::Capsule { a, b, radius } => {
+                let vertices = vec![*a, *b];
+                let normals = polygon::normals(&vertices);
+                Some((vertices, normals, *radius))
+            }
+        }
+    }
+}
+
+pub struct Manifold {
+    // Points from a towards b
+    pub normal: Vec2f,
+    // Contact points in world space with their penetration depth
+    pub points: Vec<(Vec2f, f32)>,
+}
+
+impl Manifold {
+    fn flipped(mut self) -> Self {
+        self.normal = -self.normal;
+        self
+    }
+}
+
+pub fn collide(a: &Collider, b: &Collider) -> Option<Manifold> {
+    match (a, b) {
+        // Terrain never moves, so segments do not collide with each other
+        (Collider::Segment { .. }, Collider::Segment { .. }) => None,
+        (Collider::Segment { .. }, _) => segment_collide(a, b),
+        (_, Collider::Segment { .. }) => segment_collide(b, a).map(Manifold::flipped),
+        (
+            Collider::Circle {
+                center: c_a,
+                radius: r_a,
+            },
+            Collider::Circle {
+                center: c_b,
+                radius: r_b,
+            },
+        ) => circle_circle(*c_a, *r_a, *c_b, *r_b),
+        (Collider::Polygon { vertices, normals }, Collider::Circle { center, radius }) => {
+            polygon_circle(vertices, normals, *center, *radius)
+        }
+        (Collider::Circle { center, radius }, Collider::Polygon { vertices, normals }) => {
+            polygon_circle(vertices, normals, *center, *radius).map(Manifold::flipped)
+        }
+        (Collider::Capsule { a, b, radius }, Collider::Circle { center, radius: r }) => {
+            circle_circle(closest_on_segment(*a, *b, *center), *radius, *center, *r)
+        }
+        (Collider::Circle { center, radius: r }, Collider::Capsule { a, b, radius }) => {
+            circle_circle(*center, *r, closest_on_segment(*a, *b, *center), *radius)
+        }
+        _ => {
+            let (v_a, n_a, r_a) = a.rounded().unwrap();
+            let (v_b, n_b, r_b) = b.rounded().unwrap();
+            polygon_polygon(&v_a, &n_a, r_a, &v_b, &n_b, r_b)
+        }
+    }
+}
+
+// Shorter segments have no usable direction
+const EPSILON_LENGTH: f32 = 1e-6;
+
+// Collides like a bare segment, then drops contacts that belong to a neighbouring segment.
+// Where a shape slides over the joint between two segments it can catch the corner of the
+// next one, pushing it back along the surface. Those contacts are dropped: a normal that is
+// not the face normal is only kept at a free end or at a convex corner, which the segment
+// owns at its `b` end.
+fn segment_collide(segment: &Collider, other: &Collider) -> Option<Manifold> {
+    let &Collider::Segment {
+        a,
+        b,
+        prev,
+        next,
+        one_sided,
+    } = segment
+    else {
+        return None;
+    };
+    // A zero length segment has no direction, its neighbours cover the point
+    if (b - a).length() < EPSILON_LENGTH {
+        return None;
+    }
+    let manifold = collide(&Collider::Capsule { a, b, radius: 0. }, other)?;
+    let n = manifold.normal;
+
+    let t = (b - a).norm();
+    let face = Vec2f::new(-t.y, t.x);
+    if one_sided && n.dot(&face) < 0. {
+        return None;
+    }
+    // The side the other shape is on
+    let side = if n.dot(&face) >= 0. { 1. } else { -1. };
+    if n.dot(&(face * side)) >= 1. - 1e-5 {
+        return Some(manifold);
+    }
+
+    if n.dot(&t) < 0. {
+        // Towards `a`, which the previous segment owns
+        return prev.is_none().then_some(manifold);
+    }
+    // A free end, or one whose next segment has no length to turn by
+    let Some(next) = next.filter(|next| (*next - b).length() >= EPSILON_LENGTH) else {
+        return Some(manifold);
+    };
+    let t_next = (next - b).norm();
+    let convex = t.cross(&t_next) * side < 0.;
+    (convex && n.dot(&t_next) <= 1e-3).then_some(manifold)
+}
+
+fn closest_on_segment(a: Vec2f, b: Vec2f, point: Vec2f) -> Vec2f {
+    let ab = b - a;
+    let length_sq = ab.dot(&ab);
+    if length_sq < 1e-12 {
+        return a;
+    }
+    a + ab * ((point - a).dot(&ab) / length_sq).clamp(0., 1.)
+}
+
+// Closest pair of points between two disjoint convex polygons, a point on a then one on b
+fn closest_points(v_a: &[Vec2f], v_b: &[Vec2f]) -> (Vec2f, Vec2f) {
+    let mut best = (v_a[0], v_b[0]);
+    let mut best_distance = f32::MAX;
+    let mut check = |p_a: Vec2f, p_b: Vec2f| {
+        let distance = (p_b - p_a).length();
+        if distance < best_distance {
+            best_distance = distance;
+            best = (p_a, p_b);
+        }
+    };
+    for v in v_b {
+        for j in 0..v_a.len() {
+            let edge = (v_a[j], v_a[(j + 1) % v_a.len()]);
+            check(closest_on_segment(edge.0, edge.1, *v), *v);
+        }
+    }
+    for v in v_a {
+        for j in 0..v_b.len() {
+            let edge = (v_b[j], v_b[(j + 1) % v_b.len()]);
+            check(*v, closest_on_segment(edge.0, edge.1, *v));
+        }
+    }
+    best
+}
+
+fn circle_circle(c_a: Vec2f, r_a: f32, c_b: Vec2f, r_b: f32) -> Option<Manifold> {
+    let d = c_b - c_a;
+    let distance = d.length();
+    if distance >= r_a + r_b {
+        return None;
+    }
+
+    let normal = if distance > 1e-6 {
+        d / distance
+    } else {
+        Vec2f::new(0., 1.)
+    };
+    let depth = r_a + r_b - distance;
+    Some(Manifold {
+        normal,
+        points: vec![(c_a + normal * (r_a - depth / 2.), depth)],
+    })
+}
+
+// Normal points from the polygon to the circle
+fn polygon_circle(
+    vertices: &[Vec2f],
+    normals: &[Vec2f],
+    center: Vec2f,
+    radius: f32,
+) -> Option<Manifold> {
+    // Face with the largest separation from the center
+    let mut separation = f32::MIN;
+    let mut face = 0;
+    for (i, n) in normals.iter().enumerate() {
+        let s = n.dot(&(center - vertices[i]));
+        if s > radius {
+            return None;
+        }
+        if s > separation {
+            separation = s;
+            face = i;
+        }
+    }
+
+    let v1 = vertices[face];
+    let v2 = vertices[(face + 1) % vertices.len()];
+    let face_contact = |separation: f32| Manifold {
+        normal: normals[face],
+        points: vec![(center - normals[face] * separation, radius - separation)],
+    };
+    if separation < 1e-6 {
+        // Center is inside the polygon
+        return Some(face_contact(separation));
+    }
+
+    // Which Voronoi region of the face the center lies in
+    let corner = if (center - v1).dot(&(v2 - v1)) <= 0. {
+        Some(v1)
+    } else if (center - v2).dot(&(v1 - v2)) <= 0. {
+        Some(v2)
+    } else {
+        None
+    };
+    match corner {
+        Some(corner) => {
+            let d = center - corner;
+            let distance = d.length();
+            if distance > radius {
+                return None;
+            }
+            Some(Manifold {
+                normal: d / distance,
+                points: vec![(corner, radius - distance)],
+            })
+        }
+        None => Some(face_contact(separation)),
+    }
+}
+
+// Largest separation of b's vertices along a's face normals, with the face it was found on
+fn max_separation(v_a: &[Vec2f], n_a: &[Vec2f], v_b: &[Vec2f]) -> (f32, usize) {
+    let mut best = (f32::MIN, 0);
+    for (i, n) in n_a.iter().enumerate() {
+        let s = v_b
+            .iter()
+            .map(|v| n.dot(&(*v - v_a[i])))
+            .fold(f32::MAX, f32::min);
+        if s > best.0 {
+            best = (s, i);
+        }
+    }
+    best
+}
+
+// Keeps the part of a segment where n.p <= offset
+fn clip(points: [Vec2f; 2], n: Vec2f, offset: f32) -> Option<[Vec2f; 2]> {
+    let d0 = n.dot(&points[0]) - offset;
+    let d1 = n.dot(&points[1]) - offset;
+
+    let mut out = vec![];
+    if d0 <= 0. {
+        out.push(points[0]);
+    }
+    if d1 <= 0. {
+        out.push(points[1]);
+    }
+    if d0 * d1 < 0. {
+        out.push(points[0] + (points[1] - points[0]) * (d0 / (d0 - d1)));
+    }
+    if out.len() < 2 {
+        return None;
+    }
+    Some([out[0], out[1]])
+}
+
+// Polygons with a rounding radius, so capsules are a two sided segment
+fn polygon_polygon(
+    v_a: &[Vec2f],
+    n_a: &[Vec2f],
+    r_a: f32,
+    v_b: &[Vec2f],
+    n_b: &[Vec2f],
+    r_b: f32,
+) -> Option<Manifold> {
+    let radius = r_a + r_b;
+    let (separation_a, face_a) = max_separation(v_a, n_a, v_b);
+    if separation_a > radius {
+        return None;
+    }
+    let (separation_b, face_b) = max_separation(v_b, n_b, v_a);
+    if separation_b > radius {
+        return None;
+    }
+
+    // The reference face is the one of least penetration, preferring a to avoid flip-flopping
+    let (ref_v, ref_n, inc_v, inc_n, inc_r, face, flip) = if separation_b > separation_a + 1e-3 {
+        (v_b, n_b, v_a, n_a, r_a, face_b, true)
+    } else {
+        (v_a, n_a, v_b, n_b, r_b, face_a, false)
+    };
+    let n = ref_n[face];
+
+    // Incident edge is the one facing the reference face the most
+    let incident = (0..inc_n.len())
+        .min_by(|i, j| inc_n[*i].dot(&n).total_cmp(&inc_n[*j].dot(&n)))
+        .unwrap();
+    let incident_edge = [inc_v[incident], inc_v[(incident + 1) % inc_v.len()]];
+
+    let v1 = ref_v[face];
+    let v2 = ref_v[(face + 1) % ref_v.len()];
+    let tangent = (v2 - v1).norm();
+
+    // Cut the incident edge to the sides of the reference face
+    let clipped = clip(incident_edge, -tangent, -tangent.dot(&v1))
+        .and_then(|edge| clip(edge, tangent, tangent.dot(&v2)));
+
+    let points = clipped
+        .iter()
+        .flatten()
+        .filter_map(|p| {
+            let separation = n.dot(&(*p - v1));
+            (separation <= radius).then_some((*p - n * inc_r, radius - separation))
+        })
+        .collect::<Vec<(Vec2f, f32)>>();
+    if points.is_empty() {
+        if radius > 0. {
+            // Rounded corners past the ends of the faces, touching like two circles
+            let (p_a, p_b) = closest_points(v_a, v_b);
+            return circle_circle(p_a, r_a, p_b, r_b);
+        }
+        return None;
+    }
+
+    let manifold = Manifold { normal: n, points };
+    Some(if flip { manifold.flipped() } else { manifold })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{physics::material::Material, renderer::component::TransformComponent};
+
+    fn place(shape: Shape, size: Vec2f, position: Vec2f) -> Collider {
+        let mut colliders = vec![];
+        Collider::from_shape(&shape, size, position, 0., &mut colliders);
+        colliders.remove(0)
+    }
+
+    fn circle(position: Vec2f, radius: f32) -> Collider {
+        place(Shape::Circle, Vec2f::new(radius, radius), position)
+    }
+
+    fn square(position: Vec2f, side: f32) -> Collider {
+        place(Shape::Rectangle, Vec2f::new(side, side), position)
+    }
+
+    fn hexagon(position: Vec2f, radius: f32) -> Collider {
+        let shape = Shape::regular_polygon(6, radius).unwrap();
+        place(shape, Vec2f::zero(), position)
+    }
+
+    // Normal and deepest penetration of a contact
+    fn contact(a: &Collider, b: &Collider) -> (Vec2f, f32) {
+        let manifold = collide(a, b).expect("shapes should overlap");
+        let depth = manifold
+            .points
+            .iter()
+            .map(|(_, depth)| *depth)
+            .fold(f32::MIN, f32::max);
+        (manifold.normal, depth)
+    }
+
+    fn assert_near(a: Vec2f, b: Vec2f) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn circle_circle() {
+        let a = circle(Vec2f::zero(), 1.);
+        let (normal, depth) = contact(&a, &circle(Vec2f::new(1.5, 0.), 1.));
+        assert_near(normal, Vec2f::new(1., 0.));
+        assert!((depth - 0.5).abs() < 1e-4);
+        assert!(collide(&a, &circle(Vec2f::new(2.1, 0.), 1.)).is_none());
+    }
+
+    #[test]
+    fn box_circle() {
+        let a = square(Vec2f::zero(), 2.);
+        let b = circle(Vec2f::new(0., 1.3), 0.5);
+        let (normal, depth) = contact(&a, &b);
+        assert_near(normal, Vec2f::new(0., 1.));
+        assert!((depth - 0.2).abs() < 1e-4);
+        // The normal always points from the first shape to the second
+        assert_near(contact(&b, &a).0, Vec2f::new(0., -1.));
+        assert!(collide(&a, &circle(Vec2f::new(1.4, 1.4), 0.5)).is_none());
+    }
+
+    #[test]
+    fn box_box() {
+        let a = square(Vec2f::zero(), 2.);
+        let b = square(Vec2f::new(1.6, 0.2), 2.);
+        let manifold = collide(&a, &b).expect("boxes should overlap");
+        assert_near(manifold.normal, Vec2f::new(1., 0.));
+        // Face to face, so both corners of the overlap touch
+        assert_eq!(manifold.points.len(), 2);
+        for (_, depth) in &manifold.points {
+            assert!((depth - 0.4).abs() < 1e-4);
+        }
+        assert!(collide(&a, &square(Vec2f::new(2.1, 0.), 2.)).is_none());
+    }
+
+    #[test]
+    fn polygon_pairs() {
+        // Pointing up, with its top corner at y = 1
+        let a = hexagon(Vec2f::zero(), 1.);
+        let (normal, depth) = contact(&a, &square(Vec2f::new(0., 1.3), 1.));
+        assert_near(normal, Vec2f::new(0., 1.));
+        assert!((depth - 0.2).abs() < 1e-4);
+
+        let (normal, depth) = contact(&a, &circle(Vec2f::new(0., 1.4), 0.5));
+        assert_near(normal, Vec2f::new(0., 1.));
+        assert!((depth - 0.1).abs() < 1e-4);
+
+        // The flat sides are sqrt(3) / 2 from the center
+        let (normal, depth) = contact(&a, &hexagon(Vec2f::new(1.6, 0.), 1.));
+        assert_near(normal, Vec2f::new(1., 0.));
+        assert!((depth - (3f32.sqrt() - 1.6)).abs() < 1e-4);
+
+        assert!(collide(&a, &square(Vec2f::new(0., 1.6), 1.)).is_none());
+        assert!(collide(&a, &hexagon(Vec2f::new(1.8, 0.), 1.)).is_none());
+    }
+
+    fn chain(vertices: &[(f32, f32)], one_sided: bool) -> Vec<Collider> {
+        let vertices = vertices.iter().map(|&(x, y)| Vec2f::new(x, y)).collect();
+        let shape = Shape::chain(vertices, one_sided).unwrap();
+        let mut colliders = vec![];
+        Collider::from_shape(&shape, Vec2f::zero(), Vec2f::zero(), 0., &mut colliders);
+        colliders
+    }
+
+    #[test]
+    fn chains_have_no_ghost_contacts() {
+        let ground = chain(&[(0., 0.), (1., 0.), (2., 0.), (3., 0.)], false);
+        // Sunk deeper into the ground than it overlaps the next segment near each vertex
+        for step in 0..=200 {
+            let x = 0.3 + step as f32 * 0.012;
+            let body = square(Vec2f::new(x, 0.2), 0.5);
+            let contacts = ground
+                .iter()
+                .filter_map(|segment| collide(segment, &body))
+                .collect::<Vec<Manifold>>();
+            assert!(!contacts.is_empty(), "no contact at x = {}", x);
+            for manifold in contacts {
+                // Sliding towards +x, so a normal pointing back would stop it
+                assert!(
+                    manifold.normal.x > -1e-3,
+                    "normal {:?} opposes the motion at x = {}",
+                    manifold.normal,
+                    x
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn one_sided_segments_ignore_bodies_from_behind() {
+        let ground = Shape::edge(Vec2f::zero(), Vec2f::new(2., 0.), true).unwrap();
+        let mut colliders = vec![];
+        Collider::from_shape(&ground, Vec2f::zero(), Vec2f::zero(), 0., &mut colliders);
+        let segment = &colliders[0];
+        // Drawn left to right, so solid from above
+        let (normal, _) = contact(segment, &square(Vec2f::new(1., 0.2), 0.5));
+        assert_near(normal, Vec2f::new(0., 1.));
+        assert!(collide(segment, &square(Vec2f::new(1., -0.2), 0.5)).is_none());
+        assert!(collide(&circle(Vec2f::new(1., -0.1), 0.2), segment).is_none());
+
+        let two_sided = &chain(&[(0., 0.), (2., 0.)], false)[0];
+        let (normal, _) = contact(two_sided, &square(Vec2f::new(1., -0.2), 0.5));
+        assert_near(normal, Vec2f::new(0., -1.));
+    }
+
+    #[test]
+    fn clockwise_colliders_face_out() {
+        // Scenery without a body, so it never goes through Entity::new
+        let clockwise = vec![
+            Vec2f::new(-1., -1.),
+            Vec2f::new(-1., 1.),
+            Vec2f::new(1., 1.),
+            Vec2f::new(1., -1.),
+        ];
+        let transform = TransformComponent::new(Vec2f::new(3., 2.));
+        let collider = ColliderComponent::new(
+            Shape::Polygon(clockwise.clone()),
+            Vec2f::zero(),
+            &Material::default(),
+            &transform,
+        );
+        let ledge = Collider::from_component(&collider, transform.position, 0.).remove(0);
+        let (normal, depth) = contact(&ledge, &circle(Vec2f::new(3., 3.4), 0.5));
+        assert_near(normal, Vec2f::new(0., 1.));
+        assert!((depth - 0.1).abs() < 1e-4);
+        // Left in place
+        let Shape::Polygon(vertices) = &collider.shape else {
+            panic!("still a polygon");
+        };
+        assert!(vertices.iter().all(|v| clockwise.contains(v)));
+    }
+
+    #[test]
+    fn degenerate_chains() {
+        assert!(Shape::chain(vec![Vec2f::zero()], false).is_err());
+        assert!(Shape::edge(Vec2f::zero(), Vec2f::zero(), true).is_err());
+        let repeated = vec![Vec2f::zero(), Vec2f::new(1., 0.), Vec2f::new(1., 0.)];
+        assert!(Shape::chain(repeated, false).is_err());
+        let infinite = vec![Vec2f::zero(), Vec2f::new(f32::INFINITY, 0.)];
+        assert!(Shape::chain(infinite, false).is_err());
+
+        // Built by hand, a repeated point gives no contact rather than NaN
+        let point = Collider::Segment {
+            a: Vec2f::new(1., 0.),
+            b: Vec2f::new(1., 0.),
+            prev: Some(Vec2f::zero()),
+            next: Some(Vec2f::new(2., 0.)),
+            one_sided: false,
+        };
+        assert!(collide(&point, &circle(Vec2f::new(1., 0.1), 0.2)).is_none());
+        // And its neighbour treats the end it shares as free
+        let before = Collider::Segment {
+            a: Vec2f::zero(),
+            b: Vec2f::new(1., 0.),
+            prev: None,
+            next: Some(Vec2f::new(1., 0.)),
+            one_sided: false,
+        };
+        let manifold = collide(&before, &circle(Vec2f::new(1.1, 0.1), 0.2)).unwrap();
+        assert!(manifold.normal.x.is_finite() && manifold.normal.y.is_finite());
+        assert!(
+            manifold
+                .points
+                .iter()
+                .all(|(p, depth)| p.x.is_finite() && depth.is_finite())
+        );
+    }
+
+    #[test]
+    fn polygons_are_validated() {
+        assert!(Shape::polygon(vec![]).is_err());
+        assert!(Shape::polygon(vec![Vec2f::zero(), Vec2f::new(1., 0.)]).is_err());
+        // All on one line
+        let line = vec![Vec2f::zero(), Vec2f::new(1., 0.), Vec2f::new(2., 0.)];
+        assert!(Shape::polygon(line).is_err());
+        let dart = vec![
+            Vec2f::zero(),
+            Vec2f::new(2., 1.),
+            Vec2f::new(0., 2.),
+            Vec2f::new(0.5, 1.),
+        ];
+        assert!(Shape::polygon(dart).is_err());
+        assert!(Shape::regular_polygon(2, 1.).is_err());
+        assert!(Shape::triangle(0.).is_err());
+
+        let clockwise = vec![Vec2f::zero(), Vec2f::new(0., 1.), Vec2f::new(1., 0.)];
+        assert!(Shape::polygon(clockwise).is_ok());
+    }
+}
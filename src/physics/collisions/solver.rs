@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use crate::{
+    math::math::Vec2f,
+    physics::{
+        entities::physics_body::PhysicsBody,
+        joint::{Joint, JointId},
+    },
+    renderer::{component::TransformComponent, entity::EntityId},
+};
+
+const ITERATIONS: usize = 8;
+// Penetration allowed before positions are corrected, keeps resting contacts stable
+const SLOP: f32 = 0.005;
+// Fraction of the remaining penetration removed each step
+const CORRECTION: f32 = 0.4;
+// Slower impacts do not bounce, so bodies can come to rest
+const RESTITUTION_THRESHOLD: f32 = 0.2;
+
+pub struct ContactPoint {
+    // From each body's center of mass to the contact point
+    pub r_a: Vec2f,
+    pub r_b: Vec2f,
+    pub depth: f32,
+    normal_mass: f32,
+    tangent_mass: f32,
+    bias: f32,
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+impl ContactPoint {
+    pub fn new(r_a: Vec2f, r_b: Vec2f, depth: f32) -> Self {
+        Self {
+            r_a,
+            r_b,
+            depth,
+            normal_mass: 0.,
+            tangent_mass: 0.,
+            bias: 0.,
+            normal_impulse: 0.,
+            tangent_impulse: 0.,
+        }
+    }
+}
+
+pub struct Contact {
+    pub a: EntityId,
+    pub b: EntityId,
+    // Points from a towards b
+    pub normal: Vec2f,
+    pub points: Vec<ContactPoint>,
+    // Mixed from the two colliders
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+// A body copied out of the world for its island, with its pose. Entities without a dynamic
+// body are solved as immovable
+#[derive(Clone, Copy)]
+pub struct SolverBody {
+    pub position: Vec2f,
+    pub rotation: f32,
+    pub velocity: Vec2f,
+    pub angular_velocity: f32,
+    pub inv_mass: f32,
+    pub inv_inertia: f32,
+}
+
+impl SolverBody {
+    pub fn new(transform: &TransformComponent, body: Option<&PhysicsBody>) -> Self {
+        let mut solver_body = Self {
+            position: transform.position,
+            rotation: transform.rotation,
+            velocity: Vec2f::zero(),
+            angular_velocity: 0.,
+            inv_mass: 0.,
+            inv_inertia: 0.,
+        };
+        if let Some(body) = body {
+            solver_body.velocity = body.velocity;
+            solver_body.angular_velocity = body.angular_velocity;
+            if body.is_dynamic() {
+                solver_body.inv_mass = body.inv_mass;
+                solver_body.inv_inertia = body.inv_inertia;
+            }
+        }
+        solver_body
+    }
+}
+
+// w x r for a scalar angular velocity
+fn cross_scalar(w: f32, r: Vec2f) -> Vec2f {
+    Vec2f::new(-w * r.y, w * r.x)
+}
+
+// The part of a body the velocity solver reads and writes
+#[derive(Clone, Copy)]
+pub(crate) struct Motion {
+    pub velocity: Vec2f,
+    pub angular_velocity: f32,
+    pub inv_mass: f32,
+    pub inv_inertia: f32,
+}
+
+impl Motion {
+    pub fn of(body: &SolverBody) -> Self {
+        Self {
+            velocity: body.velocity,
+            angular_velocity: body.angular_velocity,
+            inv_mass: body.inv_mass,
+            inv_inertia: body.inv_inertia,
+        }
+    }
+
+    pub fn store(&self, body: &mut SolverBody) {
+        body.velocity = self.velocity;
+        body.angular_velocity = self.angular_velocity;
+    }
+
+    pub fn velocity_at(&self, r: Vec2f) -> Vec2f {
+        self.velocity + cross_scalar(self.angular_velocity, r)
+    }
+
+    pub fn apply_impulse(&mut self, impulse: Vec2f, r: Vec2f) {
+        self.velocity += impulse * self.inv_mass;
+        self.angular_velocity += r.cross(&impulse) * self.inv_inertia;
+    }
+
+    pub fn apply_angular_impulse(&mut self, impulse: f32) {
+        self.angular_velocity += impulse * self.inv_inertia;
+    }
+}
+
+// Sequential impulses over the joints and contacts of one island, then positional correction
+pub fn solve(
+    contacts: &mut [Contact],
+    joints: &mut [(JointId, Joint)],
+    bodies: &mut HashMap<EntityId, SolverBody>,
+    dt: f32,
+) {
+    for (_, joint) in joints.iter_mut() {
+        joint.prepare(bodies);
+    }
+    for contact in contacts.iter_mut() {
+        let cor = contact.restitution;
+        let (a, b) = (
+            Motion::of(&bodies[&contact.a]),
+            Motion::of(&bodies[&contact.b]),
+        );
+        let n = contact.normal;
+        let t = Vec2f::new(-n.y, n.x);
+
+        for point in &mut contact.points {
+            // Effective mass along a direction, 1 / (1/m_a + 1/m_b + (r x d)^2 / I ...)
+            let effective_mass = |d: Vec2f| {
+                let k = a.inv_mass
+                    + b.inv_mass
+                    + point.r_a.cross(&d).powi(2) * a.inv_inertia
+                    + point.r_b.cross(&d).powi(2) * b.inv_inertia;
+                if k > 0. { 1. / k } else { 0. }
+            };
+            point.normal_mass = effective_mass(n);
+            point.tangent_mass = effective_mass(t);
+
+            let v_rel = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+            let v_n = v_rel.dot(&n);
+            point.bias = if v_n < -RESTITUTION_THRESHOLD {
+                -cor * v_n
+            } else {
+                0.
+            };
+        }
+    }
+
+    for _ in 0..ITERATIONS {
+        for (_, joint) in joints.iter_mut() {
+            joint.solve(bodies, dt);
+        }
+        for contact in contacts.iter_mut() {
+            let mu = contact.friction;
+            let mut a = Motion::of(&bodies[&contact.a]);
+            let mut b = Motion::of(&bodies[&contact.b]);
+            let n = contact.normal;
+            let t = Vec2f::new(-n.y, n.x);
+
+            for point in &mut contact.points {
+                // J = F*dt = ma*dt = m*dv => dv = J/m
+                let v_rel = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+                let lambda = point.normal_mass * (point.bias - v_rel.dot(&n));
+                // Contacts can only push, so clamp the accumulated impulse
+                let total = (point.normal_impulse + lambda).max(0.);
+                let lambda = total - point.normal_impulse;
+                point.normal_impulse = total;
+                a.apply_impulse(-n * lambda, point.r_a);
+                b.apply_impulse(n * lambda, point.r_b);
+
+                // Coulomb friction along the contact tangent, bounded by mu * j
+                let v_rel = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+                let lambda = point.tangent_mass * -v_rel.dot(&t);
+                let max_friction = mu * point.normal_impulse;
+                let total = (point.tangent_impulse + lambda).clamp(-max_friction, max_friction);
+                let lambda = total - point.tangent_impulse;
+                point.tangent_impulse = total;
+                a.apply_impulse(-t * lambda, point.r_a);
+                b.apply_impulse(t * lambda, point.r_b);
+            }
+
+            a.store(bodies.get_mut(&contact.a).unwrap());
+            b.store(bodies.get_mut(&contact.b).unwrap());
+        }
+    }
+
+    for (_, joint) in joints.iter_mut() {
+        joint.finish(dt);
+    }
+
+    for contact in contacts.iter() {
+        let depth = contact
+            .points
+            .iter()
+            .map(|point| point.depth)
+            .fold(0., f32::max);
+        let (inv_a, inv_b) = (bodies[&contact.a].inv_mass, bodies[&contact.b].inv_mass);
+        if inv_a + inv_b <= 0. {
+            continue;
+        }
+        let correction = contact.normal * ((depth - SLOP).max(0.) * CORRECTION / (inv_a + inv_b));
+        bodies.get_mut(&contact.a).unwrap().position -= correction * inv_a;
+        bodies.get_mut(&contact.b).unwrap().position += correction * inv_b;
+    }
+}
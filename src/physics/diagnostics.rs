@@ -0,0 +1,174 @@
+use crate::{
+    math::math::Vec2f,
+    physics::{
+        entities::physics_body::PhysicsBody,
+        physics_engine::{GRAVITY_CONST, GRAVITY_DIR},
+    },
+    renderer::{component::TransformComponent, entity::EntityManager},
+};
+
+// Conserved quantities of the dynamic bodies in the world.
+// Potential energy is measured from y = 0 and angular momentum around the origin. Motors and
+// the mouse add energy, so the total is only conserved without them
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostics {
+    pub kinetic: f32,
+    pub potential: f32,
+    // Elastic energy stored in the mouse joint's spring. Other joints are rigid
+    pub spring: f32,
+    // Work the joint motors did during the last step
+    pub motor_work: f32,
+    pub rotational: f32,
+    pub linear_momentum: Vec2f,
+    pub angular_momentum: f32,
+}
+
+impl Diagnostics {
+    pub fn zero() -> Self {
+        Self {
+            kinetic: 0.,
+            potential: 0.,
+            spring: 0.,
+            motor_work: 0.,
+            rotational: 0.,
+            linear_momentum: Vec2f::zero(),
+            angular_momentum: 0.,
+        }
+    }
+
+    pub fn compute(entity_manager: &EntityManager) -> Self {
+        let mut diagnostics = Self::zero();
+        let components = &entity_manager.components;
+        diagnostics.motor_work = entity_manager
+            .joints
+            .values()
+            .map(|joint| joint.motor_work())
+            .sum();
+        if let Some(joint) = &entity_manager.mouse_joint
+            && let Some(transform) = components.get::<TransformComponent>(&joint.body)
+            && let Some(body) = components.get::<PhysicsBody>(&joint.body)
+        {
+            diagnostics.spring = joint.energy(transform, body);
+        }
+
+        let g = GRAVITY_DIR * GRAVITY_CONST;
+        let Some(bodies) = components.storage::<PhysicsBody>() else {
+            return diagnostics;
+        };
+
+        for (id, body) in bodies {
+            let Some(transform) = components.get::<TransformComponent>(id) else {
+                continue;
+            };
+            if !body.is_dynamic() {
+                continue;
+            }
+            let position = transform.position;
+
+            let momentum = body.velocity * body.mass;
+            diagnostics.kinetic += 0.5 * body.mass * body.velocity.dot(&body.velocity);
+            // U = mgh, with g pointing down
+            diagnostics.potential -= body.mass * g.dot(&position);
+            diagnostics.rotational += 0.5 * body.inertia * body.angular_velocity.powi(2);
+            diagnostics.linear_momentum += momentum;
+            // L = r x p + I*w
+            diagnostics.angular_momentum +=
+                position.cross(&momentum) + body.inertia * body.angular_velocity;
+        }
+
+        diagnostics
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic + self.potential + self.spring + self.rotational
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::color::WHITE;
+
+    use crate::{
+        app::AppContext,
+        math::math::Vec2f,
+        physics::{
+            entities::physics_body::{PhysicsBody, RigidBody},
+            physics_engine::PhysicsEngine,
+        },
+        renderer::{component::ColliderComponent, entity::Shape},
+    };
+
+    fn engine() -> PhysicsEngine {
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        engine
+    }
+
+    #[test]
+    fn free_fall_conserves_energy() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        app_context.new_entity_shaped(
+            Vec2f::new(7.5, 9.),
+            Vec2f::new(0.2, 0.2),
+            WHITE,
+            Shape::Circle,
+            RigidBody::Dynamic,
+        );
+        let mut engine = engine();
+        engine.update(&mut app_context, engine.fixed_dt);
+        let start = engine.diagnostics().total_energy();
+        for _ in 0..40 {
+            engine.update(&mut app_context, engine.fixed_dt);
+        }
+        let diagnostics = engine.diagnostics();
+        // It did fall
+        assert!(diagnostics.kinetic > 0.05 * start.abs());
+        let drift = (diagnostics.total_energy() - start).abs() / start.abs();
+        assert!(drift < 0.01, "energy drifted by {}", drift);
+    }
+
+    #[test]
+    fn elastic_collision_conserves_momentum() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let mut ball = |position: Vec2f, radius: f32, velocity: Vec2f| {
+            let id = app_context.new_entity_shaped(
+                position,
+                Vec2f::new(radius, radius),
+                WHITE,
+                Shape::Circle,
+                RigidBody::Dynamic,
+            );
+            let components = &mut app_context.entity_manager.components;
+            components.get_mut::<PhysicsBody>(&id).unwrap().velocity = velocity;
+            components
+                .get_mut::<ColliderComponent>(&id)
+                .unwrap()
+                .restitution = 1.;
+            id
+        };
+        let light = ball(Vec2f::new(5., 8.), 0.2, Vec2f::new(6., 0.));
+        let heavy = ball(Vec2f::new(8., 8.), 0.4, Vec2f::new(-2., 0.));
+        let mut engine = engine();
+        engine.update(&mut app_context, engine.fixed_dt);
+        let before = *engine.diagnostics();
+        for _ in 0..30 {
+            engine.update(&mut app_context, engine.fixed_dt);
+        }
+        let after = *engine.diagnostics();
+
+        // They bounced off each other
+        let components = &app_context.entity_manager.components;
+        assert!(components.get::<PhysicsBody>(&light).unwrap().velocity.x < 0.);
+        assert!(components.get::<PhysicsBody>(&heavy).unwrap().velocity.x > -2.);
+        // Gravity only acts along y
+        let momentum = (after.linear_momentum.x - before.linear_momentum.x).abs();
+        assert!(
+            momentum < 1e-3 * before.linear_momentum.x.abs().max(1.),
+            "{}",
+            momentum
+        );
+        // Elastic, so the energy is kept too
+        let drift = (after.total_energy() - before.total_energy()).abs() / before.total_energy();
+        assert!(drift < 0.02, "energy drifted by {}", drift);
+    }
+}
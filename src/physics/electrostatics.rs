@@ -0,0 +1,132 @@
+use crate::{
+    math::math::Vec2f,
+    physics::entities::physics_body::PhysicsBody,
+    renderer::{
+        component::{ComponentManager, TransformComponent},
+        entity::EntityId,
+    },
+};
+
+// N m^2 / C^2
+pub const COULOMB_CONST: f32 = 8.99e9;
+// Keeps the force finite when two charges overlap, in meters
+const SOFTENING: f32 = 0.05;
+
+pub struct Electrostatics {
+    pub enabled: bool,
+    // Uniform external field in N/C
+    pub external_field: Vec2f,
+}
+
+// A charge fixed in place that is not attached to any body
+#[derive(Clone, Copy, Debug)]
+pub struct PointCharge {
+    pub position: Vec2f,
+    // Coulombs
+    pub charge: f32,
+}
+
+struct ChargeSource {
+    id: Option<EntityId>,
+    position: Vec2f,
+    charge: f32,
+}
+
+// Charged bodies in id order followed by the point charges, so sums are deterministic
+fn sources(components: &ComponentManager, point_charges: &[PointCharge]) -> Vec<ChargeSource> {
+    let mut sources = components
+        .storage::<PhysicsBody>()
+        .into_iter()
+        .flatten()
+        .filter(|(_, body)| body.charge != 0.)
+        .filter_map(|(id, body)| {
+            Some(ChargeSource {
+                id: Some(*id),
+                position: components.get::<TransformComponent>(id)?.position,
+                charge: body.charge,
+            })
+        })
+        .collect::<Vec<ChargeSource>>();
+    sources.extend(point_charges.iter().map(|p| ChargeSource {
+        id: None,
+        position: p.position,
+        charge: p.charge,
+    }));
+    sources
+}
+
+// E = kq r / |r|^3, ignoring the body with id `exclude` so bodies do not push themselves
+fn field_from(sources: &[ChargeSource], point: Vec2f, exclude: Option<EntityId>) -> Vec2f {
+    let mut field = Vec2f::zero();
+    for source in sources {
+        if exclude.is_some() && source.id == exclude {
+            continue;
+        }
+        let r = point - source.position;
+        let distance_sq = r.dot(&r) + SOFTENING * SOFTENING;
+        field += r * (COULOMB_CONST * source.charge / (distance_sq * distance_sq.sqrt()));
+    }
+    field
+}
+
+impl Electrostatics {
+    pub fn init() -> Self {
+        Self {
+            enabled: false,
+            external_field: Vec2f::zero(),
+        }
+    }
+
+    // Electric field at a point from every charge in the world
+    pub fn field_at(
+        &self,
+        components: &ComponentManager,
+        point_charges: &[PointCharge],
+        point: Vec2f,
+    ) -> Vec2f {
+        field_from(&sources(components, point_charges), point, None) + self.external_field
+    }
+
+    // Same as field_at for many points, collecting the charges only once
+    pub fn field_at_points(
+        &self,
+        components: &ComponentManager,
+        point_charges: &[PointCharge],
+        points: &[Vec2f],
+    ) -> Vec<Vec2f> {
+        let sources = sources(components, point_charges);
+        points
+            .iter()
+            .map(|point| field_from(&sources, *point, None) + self.external_field)
+            .collect()
+    }
+
+    // The charges as they are at the start of a step, None while Coulomb forces are off
+    pub fn charges(
+        &self,
+        components: &ComponentManager,
+        point_charges: &[PointCharge],
+    ) -> Option<Charges> {
+        if !self.enabled {
+            return None;
+        }
+        Some(Charges {
+            sources: sources(components, point_charges),
+            external_field: self.external_field,
+        })
+    }
+}
+
+// Every charge frozen for one step. The integrator asks it for the force on a body at each
+// position it tries, so the force follows the body through the step
+pub struct Charges {
+    sources: Vec<ChargeSource>,
+    external_field: Vec2f,
+}
+
+impl Charges {
+    // F = qE on the body `id` carrying `charge` if it were at `position`
+    pub fn force_on(&self, id: EntityId, charge: f32, position: Vec2f) -> Vec2f {
+        (field_from(&self.sources, position, Some(id)) + self.external_field) * charge
+    }
+}
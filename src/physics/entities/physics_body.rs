@@ -1,12 +1,6 @@
-use macroquad::{
-    color::BLUE,
-    shapes::draw_line,
-    window::{screen_height, screen_width},
-};
+use crate::{math::math::Vec2f, renderer::component::TransformComponent};
 
-use crate::math::math::{Vec2f, Vec2i};
-
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum RigidBody {
     Dynamic,
     Static,
@@ -55,33 +49,120 @@ impl BoundingBox {
     }
 }
 
+// A force that keeps acting on a body for `remaining` seconds
+#[derive(Clone, Copy, Debug)]
 pub struct Force {
-    pub direction: Vec2i,
-    pub magnitude: f32,
+    pub force: Vec2f,
+    // Point of application relative to the center of mass, in the body's unrotated frame
+    pub offset: Vec2f,
+    pub remaining: f32,
 }
 
+// How an entity moves. Its pose is in the TransformComponent
+#[derive(Clone)]
 pub struct PhysicsBody {
-    pub position: Vec2f,
+    pub rigidbody: RigidBody,
     pub velocity: Vec2f,
     pub angular_velocity: f32,
     pub acceleration: Vec2f,
     pub mass: f32,
     pub inv_mass: f32,
-    pub cor: f32,
+    pub inertia: f32,
+    pub inv_inertia: f32,
+    // Coulombs
+    pub charge: f32,
     pub force_accumulator: Vec2f,
+    pub torque_accumulator: f32,
+    pub timed_forces: Vec<Force>,
 }
 
 impl PhysicsBody {
-    pub fn new(position: Vec2f, mass: f32, cor: f32) -> Self {
+    pub fn new(mass: f32, rigidbody: RigidBody) -> Self {
         PhysicsBody {
-            position,
+            rigidbody,
             velocity: Vec2f::zero(),
             angular_velocity: 0.,
             acceleration: Vec2f::zero(),
             mass,
             inv_mass: if mass <= 0. { 0. } else { 1. / mass },
-            cor,
+            inertia: 0.,
+            inv_inertia: 0.,
+            charge: 0.,
             force_accumulator: Vec2f::zero(),
+            torque_accumulator: 0.,
+            timed_forces: vec![],
+        }
+    }
+
+    // Moved by forces and collisions. Static bodies and bodies without mass stay put
+    pub fn is_dynamic(&self) -> bool {
+        self.rigidbody == RigidBody::Dynamic && self.mass > 0.
+    }
+
+    pub fn set_inertia(&mut self, inertia: f32) {
+        self.inertia = inertia;
+        self.inv_inertia = if inertia <= 0. { 0. } else { 1. / inertia };
+    }
+
+    // Forces are accumulated and applied during the next step
+    pub fn apply_force(&mut self, force: Vec2f) {
+        self.force_accumulator += force;
+    }
+
+    // A force off the center of mass also produces a torque, t = r x F
+    pub fn apply_force_at_point(
+        &mut self,
+        transform: &TransformComponent,
+        force: Vec2f,
+        point: Vec2f,
+    ) {
+        self.force_accumulator += force;
+        self.torque_accumulator += (point - transform.position).cross(&force);
+    }
+
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.torque_accumulator += torque;
+    }
+
+    // Impulses change the velocity immediately, dv = J/m
+    pub fn apply_impulse(&mut self, impulse: Vec2f) {
+        self.velocity += impulse * self.inv_mass;
+    }
+
+    pub fn apply_impulse_at_point(
+        &mut self,
+        transform: &TransformComponent,
+        impulse: Vec2f,
+        point: Vec2f,
+    ) {
+        self.velocity += impulse * self.inv_mass;
+        self.angular_velocity += (point - transform.position).cross(&impulse) * self.inv_inertia;
+    }
+
+    // Keeps applying `force` at `point` for `duration` seconds. The point moves with the body
+    pub fn apply_timed_force(
+        &mut self,
+        transform: &TransformComponent,
+        force: Vec2f,
+        point: Vec2f,
+        duration: f32,
+    ) {
+        self.timed_forces.push(Force {
+            force,
+            offset: (point - transform.position).rotate(-transform.rotation),
+            remaining: duration,
+        });
+    }
+
+    // Applies the timed forces for this step and drops the ones that ran out
+    pub fn apply_timed_forces(&mut self, transform: &TransformComponent, dt: f32) {
+        let mut timed_forces = std::mem::take(&mut self.timed_forces);
+        for force in &mut timed_forces {
+            let point = transform.position + force.offset.rotate(transform.rotation);
+            self.apply_force_at_point(transform, force.force, point);
+            force.remaining -= dt;
         }
+        timed_forces.retain(|force| force.remaining > 0.);
+        self.timed_forces = timed_forces;
     }
 }
@@ -0,0 +1,114 @@
+use crate::math::math::Vec2f;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct ForceFieldId(pub usize);
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Falloff {
+    Constant,
+    // Fades to zero at the edge of the field
+    Linear,
+    InverseSquare,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ForceFieldKind {
+    // Pushes along `direction`, which is randomly rotated by up to `turbulence` radians
+    Wind { direction: Vec2f, turbulence: f32 },
+    // Pushes counter-clockwise around the center, clockwise for negative strength
+    Vortex { falloff: Falloff },
+    // Pulls towards the center, pushes away for negative strength
+    Radial { falloff: Falloff },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ForceField {
+    pub position: Vec2f,
+    // Bodies further away than this are not affected
+    pub radius: f32,
+    // Newtons
+    pub strength: f32,
+    pub kind: ForceFieldKind,
+}
+
+impl ForceField {
+    pub fn wind(position: Vec2f, radius: f32, strength: f32, direction: Vec2f) -> Self {
+        Self {
+            position,
+            radius,
+            strength,
+            kind: ForceFieldKind::Wind {
+                direction: direction.norm(),
+                turbulence: 0.,
+            },
+        }
+    }
+
+    pub fn vortex(position: Vec2f, radius: f32, strength: f32) -> Self {
+        Self {
+            position,
+            radius,
+            strength,
+            kind: ForceFieldKind::Vortex {
+                falloff: Falloff::Linear,
+            },
+        }
+    }
+
+    pub fn radial(position: Vec2f, radius: f32, strength: f32, falloff: Falloff) -> Self {
+        Self {
+            position,
+            radius,
+            strength,
+            kind: ForceFieldKind::Radial { falloff },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            ForceFieldKind::Wind { .. } => "Wind",
+            ForceFieldKind::Vortex { .. } => "Vortex",
+            ForceFieldKind::Radial { .. } if self.strength >= 0. => "Attractor",
+            ForceFieldKind::Radial { .. } => "Repulsor",
+        }
+    }
+
+    fn falloff(&self, falloff: Falloff, distance: f32) -> f32 {
+        match falloff {
+            Falloff::Constant => 1.,
+            Falloff::Linear => (1. - distance / self.radius).max(0.),
+            // Clamped so bodies passing through the center are not flung away
+            Falloff::InverseSquare => 1. / distance.powi(2).max(0.01),
+        }
+    }
+
+    // Force on a body at `point`. `time` drives the wind turbulence
+    pub fn force_at(&self, point: Vec2f, time: f32) -> Vec2f {
+        let offset = self.position - point;
+        let distance = offset.length();
+        if distance > self.radius {
+            return Vec2f::zero();
+        }
+
+        match self.kind {
+            ForceFieldKind::Wind {
+                direction,
+                turbulence,
+            } => {
+                // Cheap deterministic noise, smooth in both space and time
+                let noise =
+                    ((1.7 * point.x + 2.3 * time).sin() + (2.9 * point.y - 1.3 * time).sin()) / 2.;
+                direction.rotate(turbulence * noise) * self.strength
+            }
+            _ if distance < 1e-6 => Vec2f::zero(),
+            ForceFieldKind::Vortex { falloff } => {
+                // Perpendicular to the direction from the center
+                let tangent = Vec2f::new(offset.y, -offset.x) / distance;
+                tangent * self.strength * self.falloff(falloff, distance)
+            }
+            ForceFieldKind::Radial { falloff } => {
+                offset / distance * self.strength * self.falloff(falloff, distance)
+            }
+        }
+    }
+}
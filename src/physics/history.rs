@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+
+use crate::renderer::entity::EntityManager;
+
+// The world at one recorded step
+#[derive(Clone)]
+pub struct Snapshot {
+    pub entity_manager: EntityManager,
+    pub steps: u64,
+    pub time: f32,
+}
+
+// The most recent snapshots, oldest first. Once full the oldest one is dropped
+pub struct History {
+    pub snapshots: VecDeque<Snapshot>,
+    pub capacity: usize,
+    // Steps between two snapshots
+    pub interval: u64,
+    // Snapshot being looked at while scrubbing, None while running live
+    pub cursor: Option<usize>,
+}
+
+impl History {
+    pub fn init() -> Self {
+        // 30 seconds at 60 steps per second
+        Self::new(300, 6)
+    }
+
+    pub fn new(capacity: usize, interval: u64) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+            interval: interval.max(1),
+            cursor: None,
+        }
+    }
+
+    // Whether the world should be recorded after step number `steps`
+    pub fn is_due(&self, steps: u64) -> bool {
+        self.capacity > 0 && steps.is_multiple_of(self.interval)
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    // Moves the cursor to snapshot `index` and returns it
+    pub fn seek(&mut self, index: usize) -> Option<&Snapshot> {
+        let snapshot = self.snapshots.get(index)?;
+        self.cursor = Some(index);
+        Some(snapshot)
+    }
+
+    // Continuing from the cursor replaces everything recorded after it
+    pub fn resume(&mut self) {
+        if let Some(index) = self.cursor.take() {
+            self.snapshots.truncate(index + 1);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.cursor = None;
+    }
+}
@@ -0,0 +1,64 @@
+use std::ops::{Add, Mul};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Integrator {
+    SemiImplicitEuler,
+    VelocityVerlet,
+    Rk4,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 3] = [
+        Integrator::SemiImplicitEuler,
+        Integrator::VelocityVerlet,
+        Integrator::Rk4,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::SemiImplicitEuler => "Semi-implicit Euler",
+            Integrator::VelocityVerlet => "Velocity Verlet",
+            Integrator::Rk4 => "RK4",
+        }
+    }
+
+    // Advances a body by dt. `accel` returns the acceleration for a given position and velocity.
+    // Returns the new position, the new velocity and the acceleration at the start of the step.
+    // T is Vec2f for translation and f32 for rotation
+    pub fn step<T>(&self, position: T, velocity: T, dt: f32, accel: impl Fn(T, T) -> T) -> (T, T, T)
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let a = accel(position, velocity);
+        match self {
+            Integrator::SemiImplicitEuler => {
+                let v = velocity + a * dt;
+                (position + v * dt, v, a)
+            }
+            Integrator::VelocityVerlet => {
+                let v_half = velocity + a * (dt / 2.);
+                let x = position + v_half * dt;
+                let a_new = accel(x, v_half);
+                (x, v_half + a_new * (dt / 2.), a)
+            }
+            Integrator::Rk4 => {
+                let (k1_x, k1_v) = (velocity, a);
+
+                let k2_x = velocity + k1_v * (dt / 2.);
+                let k2_v = accel(position + k1_x * (dt / 2.), k2_x);
+
+                let k3_x = velocity + k2_v * (dt / 2.);
+                let k3_v = accel(position + k2_x * (dt / 2.), k3_x);
+
+                let k4_x = velocity + k3_v * dt;
+                let k4_v = accel(position + k3_x * dt, k4_x);
+
+                (
+                    position + (k1_x + k2_x * 2. + k3_x * 2. + k4_x) * (dt / 6.),
+                    velocity + (k1_v + k2_v * 2. + k3_v * 2. + k4_v) * (dt / 6.),
+                    a,
+                )
+            }
+        }
+    }
+}
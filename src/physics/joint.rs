@@ -0,0 +1,661 @@
+use std::collections::HashMap;
+
+use crate::{
+    math::math::Vec2f,
+    physics::{
+        collisions::solver::{Motion, SolverBody},
+        entities::physics_body::PhysicsBody,
+    },
+    renderer::{component::TransformComponent, entity::EntityId},
+};
+
+// Fraction of the position error fed back into the velocities each step
+const BAUMGARTE: f32 = 0.2;
+// A weak spring on the spin of a body held by the mouse, so it settles instead of looping
+// around the cursor forever
+const MOUSE_ANGULAR_FREQUENCY: f32 = 0.5;
+const MOUSE_ANGULAR_DAMPING: f32 = 0.1;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct JointId(pub usize);
+
+// Drives the joint towards a relative speed without exceeding a force
+#[derive(Clone, Copy, Debug)]
+pub struct Motor {
+    // rad/s for revolute joints, m/s for prismatic ones
+    pub speed: f32,
+    // N*m for revolute joints, N for prismatic ones
+    pub max_force: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JointKind {
+    // The bodies share the anchor and turn freely around it
+    Revolute,
+    // The bodies slide along an axis fixed in a without turning
+    Prismatic { local_axis: Vec2f },
+}
+
+#[derive(Clone, Debug)]
+pub struct Joint {
+    pub a: EntityId,
+    pub b: EntityId,
+    pub kind: JointKind,
+    // Anchors in each body's own frame
+    pub local_anchor_a: Vec2f,
+    pub local_anchor_b: Vec2f,
+    // Rotation of b relative to a when the joint was made
+    pub reference_angle: f32,
+    // Radians for revolute joints, meters along the axis for prismatic ones
+    pub limits: Option<(f32, f32)>,
+    pub motor: Option<Motor>,
+    // Reaction force in newtons above which the joint breaks
+    pub break_force: Option<f32>,
+    // Force the joint applied to keep the bodies together during the last step
+    pub reaction_force: f32,
+
+    // Solver state. Impulses carry over between steps to warm start the solver
+    r_a: Vec2f,
+    r_b: Vec2f,
+    d: Vec2f,
+    axis: Vec2f,
+    perp: Vec2f,
+    angle: f32,
+    // Revolute point constraint
+    point_impulse: Vec2f,
+    // Prismatic perpendicular and angular constraints
+    perp_impulse: f32,
+    angular_impulse: f32,
+    lower_impulse: f32,
+    upper_impulse: f32,
+    motor_impulse: f32,
+}
+
+impl Joint {
+    fn new(
+        a: (EntityId, &TransformComponent),
+        b: (EntityId, &TransformComponent),
+        kind: JointKind,
+        anchor: Vec2f,
+    ) -> Self {
+        let (id_a, body_a) = a;
+        let (id_b, body_b) = b;
+        Self {
+            a: id_a,
+            b: id_b,
+            kind,
+            local_anchor_a: (anchor - body_a.position).rotate(-body_a.rotation),
+            local_anchor_b: (anchor - body_b.position).rotate(-body_b.rotation),
+            reference_angle: body_b.rotation - body_a.rotation,
+            limits: None,
+            motor: None,
+            break_force: None,
+            reaction_force: 0.,
+            r_a: Vec2f::zero(),
+            r_b: Vec2f::zero(),
+            d: Vec2f::zero(),
+            axis: Vec2f::zero(),
+            perp: Vec2f::zero(),
+            angle: 0.,
+            point_impulse: Vec2f::zero(),
+            perp_impulse: 0.,
+            angular_impulse: 0.,
+            lower_impulse: 0.,
+            upper_impulse: 0.,
+            motor_impulse: 0.,
+        }
+    }
+
+    // Pins a and b together at `anchor`, in world space
+    pub fn revolute(
+        a: (EntityId, &TransformComponent),
+        b: (EntityId, &TransformComponent),
+        anchor: Vec2f,
+    ) -> Self {
+        Self::new(a, b, JointKind::Revolute, anchor)
+    }
+
+    // Lets b slide along `axis` through `anchor`, both in world space
+    pub fn prismatic(
+        a: (EntityId, &TransformComponent),
+        b: (EntityId, &TransformComponent),
+        anchor: Vec2f,
+        axis: Vec2f,
+    ) -> Self {
+        let local_axis = axis.norm().rotate(-a.1.rotation);
+        Self::new(a, b, JointKind::Prismatic { local_axis }, anchor)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            JointKind::Revolute => "Revolute",
+            JointKind::Prismatic { .. } => "Prismatic",
+        }
+    }
+
+    // Anchor positions in world space
+    pub fn anchors(
+        &self,
+        body_a: &TransformComponent,
+        body_b: &TransformComponent,
+    ) -> (Vec2f, Vec2f) {
+        (
+            body_a.position + self.local_anchor_a.rotate(body_a.rotation),
+            body_b.position + self.local_anchor_b.rotate(body_b.rotation),
+        )
+    }
+
+    // Work the motor did on the bodies during the last step, impulse times the speed it drives
+    pub fn motor_work(&self) -> f32 {
+        self.motor
+            .map_or(0., |motor| self.motor_impulse * motor.speed)
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.break_force
+            .is_some_and(|break_force| self.reaction_force > break_force)
+    }
+
+    // Total linear impulse at the anchor and angular impulse on b
+    fn impulses(&self) -> (Vec2f, f32) {
+        let axial = self.motor_impulse + self.lower_impulse - self.upper_impulse;
+        match self.kind {
+            JointKind::Revolute => (self.point_impulse, axial),
+            JointKind::Prismatic { .. } => (
+                self.axis * axial + self.perp * self.perp_impulse,
+                self.angular_impulse,
+            ),
+        }
+    }
+
+    // Lever arm on a. For prismatic joints it reaches from a's center to b's anchor
+    fn arm_a(&self) -> Vec2f {
+        match self.kind {
+            JointKind::Revolute => self.r_a,
+            JointKind::Prismatic { .. } => self.d + self.r_a,
+        }
+    }
+
+    pub fn prepare(&mut self, bodies: &mut HashMap<EntityId, SolverBody>) {
+        let (body_a, body_b) = (&bodies[&self.a], &bodies[&self.b]);
+        self.r_a = self.local_anchor_a.rotate(body_a.rotation);
+        self.r_b = self.local_anchor_b.rotate(body_b.rotation);
+        self.d = (body_b.position + self.r_b) - (body_a.position + self.r_a);
+        self.angle = body_b.rotation - body_a.rotation - self.reference_angle;
+        let position = match self.kind {
+            JointKind::Revolute => self.angle,
+            JointKind::Prismatic { local_axis } => {
+                self.axis = local_axis.rotate(body_a.rotation);
+                self.perp = Vec2f::new(-self.axis.y, self.axis.x);
+                self.axis.dot(&self.d)
+            }
+        };
+
+        // Drop impulses from limits and motors that are no longer active
+        let (lower, upper) = self.limits.unwrap_or((f32::MIN, f32::MAX));
+        if position > lower {
+            self.lower_impulse = 0.;
+        }
+        if position < upper {
+            self.upper_impulse = 0.;
+        }
+        if self.motor.is_none() {
+            self.motor_impulse = 0.;
+        }
+
+        // Warm start from last step's impulses
+        let (linear, angular) = self.impulses();
+        let mut a = Motion::of(&bodies[&self.a]);
+        let mut b = Motion::of(&bodies[&self.b]);
+        a.apply_impulse(-linear, self.arm_a());
+        b.apply_impulse(linear, self.r_b);
+        a.apply_angular_impulse(-angular);
+        b.apply_angular_impulse(angular);
+        a.store(bodies.get_mut(&self.a).unwrap());
+        b.store(bodies.get_mut(&self.b).unwrap());
+    }
+
+    // One velocity iteration. Limits and the motor go first so the joint itself wins
+    pub fn solve(&mut self, bodies: &mut HashMap<EntityId, SolverBody>, dt: f32) {
+        let mut a = Motion::of(&bodies[&self.a]);
+        let mut b = Motion::of(&bodies[&self.b]);
+        match self.kind {
+            JointKind::Revolute => self.solve_revolute(&mut a, &mut b, dt),
+            JointKind::Prismatic { .. } => self.solve_prismatic(&mut a, &mut b, dt),
+        }
+        a.store(bodies.get_mut(&self.a).unwrap());
+        b.store(bodies.get_mut(&self.b).unwrap());
+    }
+
+    // Clamps an accumulated impulse and returns the change to apply
+    fn accumulate(total: &mut f32, lambda: f32, min: f32, max: f32) -> f32 {
+        let old = *total;
+        *total = (old + lambda).clamp(min, max);
+        *total - old
+    }
+
+    fn solve_revolute(&mut self, a: &mut Motion, b: &mut Motion, dt: f32) {
+        let k33 = a.inv_inertia + b.inv_inertia;
+        if let Some(motor) = self.motor
+            && k33 > 0.
+        {
+            let cdot = b.angular_velocity - a.angular_velocity - motor.speed;
+            let max = motor.max_force * dt;
+            let lambda = Self::accumulate(&mut self.motor_impulse, -cdot / k33, -max, max);
+            a.apply_angular_impulse(-lambda);
+            b.apply_angular_impulse(lambda);
+        }
+
+        // Point to point, K = [1/m_a + 1/m_b + (r x)^2 / I ...] as a 2x2 matrix
+        let (r_a, r_b) = (self.r_a, self.r_b);
+        let m = a.inv_mass + b.inv_mass;
+        let k11 = m + a.inv_inertia * r_a.y * r_a.y + b.inv_inertia * r_b.y * r_b.y;
+        let k12 = -a.inv_inertia * r_a.x * r_a.y - b.inv_inertia * r_b.x * r_b.y;
+        let k22 = m + a.inv_inertia * r_a.x * r_a.x + b.inv_inertia * r_b.x * r_b.x;
+        // How an angular impulse moves the anchors apart
+        let k13 = -a.inv_inertia * r_a.y - b.inv_inertia * r_b.y;
+        let k23 = a.inv_inertia * r_a.x + b.inv_inertia * r_b.x;
+        let cdot = b.velocity_at(r_b) - a.velocity_at(r_a) + self.d * (BAUMGARTE / dt);
+
+        // An active limit is solved together with the point, otherwise a light body
+        // spins around its own center instead of swinging around the anchor
+        let limit = self.limits.and_then(|(lower, upper)| {
+            if self.angle <= lower {
+                Some((self.angle - lower, self.lower_impulse, 1.))
+            } else if self.angle >= upper {
+                Some((self.angle - upper, -self.upper_impulse, -1.))
+            } else {
+                None
+            }
+        });
+        if let Some((c, total, sign)) = limit
+            && k33 > 0.
+        {
+            let cdot_angle = b.angular_velocity - a.angular_velocity + BAUMGARTE / dt * c;
+            let k = [[k11, k12, k13], [k12, k22, k23], [k13, k23, k33]];
+            if let Some(lambda) = solve3(k, [-cdot.x, -cdot.y, -cdot_angle]) {
+                let (point, angular) = if (total + lambda[2]) * sign >= 0. {
+                    (Vec2f::new(lambda[0], lambda[1]), lambda[2])
+                } else {
+                    // The limit would pull, so release it and hold only the point
+                    let rhs = -cdot + Vec2f::new(k13, k23) * total;
+                    match solve2(k11, k12, k22, rhs) {
+                        Some(point) => (point, -total),
+                        None => return,
+                    }
+                };
+                let total = total + angular;
+                if sign > 0. {
+                    self.lower_impulse = total;
+                } else {
+                    self.upper_impulse = -total;
+                }
+                self.point_impulse += point;
+                a.apply_impulse(-point, r_a);
+                b.apply_impulse(point, r_b);
+                a.apply_angular_impulse(-angular);
+                b.apply_angular_impulse(angular);
+                return;
+            }
+        }
+
+        let Some(lambda) = solve2(k11, k12, k22, -cdot) else {
+            return;
+        };
+        self.point_impulse += lambda;
+        a.apply_impulse(-lambda, r_a);
+        b.apply_impulse(lambda, r_b);
+    }
+
+    fn solve_prismatic(&mut self, a: &mut Motion, b: &mut Motion, dt: f32) {
+        let (axis, perp) = (self.axis, self.perp);
+        let arm_a = self.arm_a();
+        let r_b = self.r_b;
+        let m = a.inv_mass + b.inv_mass;
+
+        let axial_speed = |a: &Motion, b: &Motion| {
+            axis.dot(&(b.velocity - a.velocity)) + r_b.cross(&axis) * b.angular_velocity
+                - arm_a.cross(&axis) * a.angular_velocity
+        };
+        let k_axis = m
+            + a.inv_inertia * arm_a.cross(&axis).powi(2)
+            + b.inv_inertia * r_b.cross(&axis).powi(2);
+        if k_axis > 0. {
+            let push = |a: &mut Motion, b: &mut Motion, lambda: f32| {
+                a.apply_impulse(-axis * lambda, arm_a);
+                b.apply_impulse(axis * lambda, r_b);
+            };
+            if let Some(motor) = self.motor {
+                let cdot = axial_speed(a, b) - motor.speed;
+                let max = motor.max_force * dt;
+                let lambda = Self::accumulate(&mut self.motor_impulse, -cdot / k_axis, -max, max);
+                push(a, b, lambda);
+            }
+            if let Some((lower, upper)) = self.limits {
+                let translation = axis.dot(&self.d);
+                if translation <= lower {
+                    let c = translation - lower;
+                    let lambda = -(axial_speed(a, b) + BAUMGARTE / dt * c) / k_axis;
+                    let lambda = Self::accumulate(&mut self.lower_impulse, lambda, 0., f32::MAX);
+                    push(a, b, lambda);
+                } else if translation >= upper {
+                    let c = upper - translation;
+                    let lambda = -(-axial_speed(a, b) + BAUMGARTE / dt * c) / k_axis;
+                    let lambda = Self::accumulate(&mut self.upper_impulse, lambda, 0., f32::MAX);
+                    push(a, b, -lambda);
+                }
+            }
+        }
+
+        // No turning relative to each other
+        let k = a.inv_inertia + b.inv_inertia;
+        if k > 0. {
+            let cdot = b.angular_velocity - a.angular_velocity;
+            let lambda = -(cdot + BAUMGARTE / dt * self.angle) / k;
+            self.angular_impulse += lambda;
+            a.apply_angular_impulse(-lambda);
+            b.apply_angular_impulse(lambda);
+        }
+
+        // No sliding off the axis
+        let k_perp = m
+            + a.inv_inertia * arm_a.cross(&perp).powi(2)
+            + b.inv_inertia * r_b.cross(&perp).powi(2);
+        if k_perp > 0. {
+            let cdot = perp.dot(&(b.velocity - a.velocity)) + r_b.cross(&perp) * b.angular_velocity
+                - arm_a.cross(&perp) * a.angular_velocity;
+            let lambda = -(cdot + BAUMGARTE / dt * perp.dot(&self.d)) / k_perp;
+            self.perp_impulse += lambda;
+            a.apply_impulse(-perp * lambda, arm_a);
+            b.apply_impulse(perp * lambda, r_b);
+        }
+    }
+
+    // Called once the iterations are done
+    pub fn finish(&mut self, dt: f32) {
+        self.reaction_force = self.impulses().0.length() / dt;
+    }
+}
+
+// Pulls a point of one body towards a target, like a spring that gives way past `max_force`.
+// Used to drag bodies with the mouse
+#[derive(Clone, Copy, Debug)]
+pub struct MouseJoint {
+    pub body: EntityId,
+    // Grabbed point in the body's own frame
+    pub local_anchor: Vec2f,
+    // Where the point is pulled to, in world space
+    pub target: Vec2f,
+    pub max_force: f32,
+    // Stiffness as the spring's natural frequency in Hz, and its damping ratio
+    pub frequency: f32,
+    pub damping_ratio: f32,
+}
+
+impl MouseJoint {
+    // Grabs the body at `point`, in world space
+    pub fn new(body: (EntityId, &TransformComponent), point: Vec2f, max_force: f32) -> Self {
+        let (id, transform) = body;
+        Self {
+            body: id,
+            local_anchor: (point - transform.position).rotate(-transform.rotation),
+            target: point,
+            max_force,
+            frequency: 5.,
+            damping_ratio: 0.7,
+        }
+    }
+
+    // Grabbed point in world space
+    pub fn anchor(&self, transform: &TransformComponent) -> Vec2f {
+        transform.position + self.local_anchor.rotate(transform.rotation)
+    }
+
+    // Spring stiffness for a body of `mass`, k = m w^2
+    fn stiffness(&self, mass: f32) -> f32 {
+        let omega = 2. * std::f32::consts::PI * self.frequency;
+        mass * omega * omega
+    }
+
+    // Elastic energy in the spring, 1/2 k x^2
+    pub fn energy(&self, transform: &TransformComponent, body: &PhysicsBody) -> f32 {
+        let stretch = self.anchor(transform) - self.target;
+        0.5 * self.stiffness(body.mass) * stretch.dot(&stretch)
+    }
+
+    // Pulls for one step. The spring is solved implicitly as a soft constraint, so it stays
+    // stable however stiff it is compared to the step
+    pub fn apply(&self, transform: &TransformComponent, body: &mut PhysicsBody, dt: f32) {
+        if !body.is_dynamic() || dt <= 0. {
+            return;
+        }
+        // Soft constraint towards no spin, without a position target
+        let omega = 2. * std::f32::consts::PI * MOUSE_ANGULAR_FREQUENCY;
+        let a1 = 2. * MOUSE_ANGULAR_DAMPING + dt * omega;
+        let a2 = dt * omega * a1;
+        body.angular_velocity *= 1. - a2 / (1. + a2);
+
+        let omega = 2. * std::f32::consts::PI * self.frequency;
+        let stiffness = self.stiffness(body.mass);
+        let damping = 2. * body.mass * self.damping_ratio * omega;
+        let gamma = 1. / (dt * (damping + dt * stiffness));
+        let beta = dt * stiffness * gamma;
+
+        let anchor = self.anchor(transform);
+        let r = anchor - transform.position;
+        let (inv_mass, inv_inertia) = (body.inv_mass, body.inv_inertia);
+        let k11 = inv_mass + inv_inertia * r.y * r.y + gamma;
+        let k12 = -inv_inertia * r.x * r.y;
+        let k22 = inv_mass + inv_inertia * r.x * r.x + gamma;
+        let velocity = body.velocity + Vec2f::new(-r.y, r.x) * body.angular_velocity;
+        let cdot = velocity + (anchor - self.target) * beta;
+        let Some(mut impulse) = solve2(k11, k12, k22, -cdot) else {
+            return;
+        };
+
+        let max = self.max_force * dt;
+        if impulse.length() > max {
+            impulse = impulse.norm() * max;
+        }
+        body.apply_impulse_at_point(transform, impulse, anchor);
+    }
+}
+
+// Solves the symmetric system [k11 k12; k12 k22] x = rhs
+fn solve2(k11: f32, k12: f32, k22: f32, rhs: Vec2f) -> Option<Vec2f> {
+    let det = k11 * k22 - k12 * k12;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some(Vec2f::new(
+        (k22 * rhs.x - k12 * rhs.y) / det,
+        (k11 * rhs.y - k12 * rhs.x) / det,
+    ))
+}
+
+// Cramer's rule for k x = rhs
+fn solve3(k: [[f32; 3]; 3], rhs: [f32; 3]) -> Option<[f32; 3]> {
+    let det3 = |m: [[f32; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let det = det3(k);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let mut x = [0.; 3];
+    for (column, x) in x.iter_mut().enumerate() {
+        let mut m = k;
+        for row in 0..3 {
+            m[row][column] = rhs[row];
+        }
+        *x = det3(m) / det;
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::color::WHITE;
+
+    use super::*;
+    use crate::{
+        app::{App, AppContext, Event},
+        physics::{entities::physics_body::RigidBody, physics_engine::PhysicsEngine},
+        renderer::{component::ColliderComponent, entity::Shape},
+    };
+
+    const PIVOT: Vec2f = Vec2f { x: 7.5, y: 8. };
+
+    // A ball hanging off a fixed pivot, starting level with it
+    fn pendulum() -> (AppContext, EntityId, EntityId, JointId) {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let pivot = app_context.new_entity_shaped(
+            PIVOT,
+            Vec2f::new(0.1, 0.1),
+            WHITE,
+            Shape::Rectangle,
+            RigidBody::Static,
+        );
+        let ball = app_context.new_entity_shaped(
+            PIVOT + Vec2f::new(1., 0.),
+            Vec2f::new(0.1, 0.1),
+            WHITE,
+            Shape::Circle,
+            RigidBody::Dynamic,
+        );
+        let joint = app_context.add_revolute_joint(pivot, ball, PIVOT).unwrap();
+        (app_context, pivot, ball, joint)
+    }
+
+    fn engine() -> PhysicsEngine {
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        engine
+    }
+
+    fn transform(app_context: &AppContext, id: EntityId) -> TransformComponent {
+        *app_context
+            .entity_manager
+            .components
+            .get::<TransformComponent>(&id)
+            .unwrap()
+    }
+
+    #[test]
+    fn pendulum_keeps_its_length() {
+        let (mut app_context, _, ball, _) = pendulum();
+        let mut engine = engine();
+        let mut lowest = PIVOT.y;
+        for _ in 0..240 {
+            engine.update(&mut app_context, engine.fixed_dt);
+            let position = transform(&app_context, ball).position;
+            let length = (position - PIVOT).length();
+            // Stretches a little at the bottom, where the pull is strongest
+            assert!((length - 1.).abs() < 0.03, "length {}", length);
+            lowest = lowest.min(position.y);
+        }
+        // It did swing down through the bottom
+        assert!(lowest < PIVOT.y - 0.95);
+    }
+
+    #[test]
+    fn limits_clamp_the_angle() {
+        let (mut app_context, _, ball, joint) = pendulum();
+        app_context
+            .entity_manager
+            .joints
+            .get_mut(&joint)
+            .unwrap()
+            .limits = Some((-0.3, 0.3));
+        let mut engine = engine();
+        for _ in 0..240 {
+            engine.update(&mut app_context, engine.fixed_dt);
+            // Gravity swings it clockwise, towards the lower limit
+            let rotation = transform(&app_context, ball).rotation;
+            assert!(rotation > -0.32, "rotation {}", rotation);
+        }
+        let position = transform(&app_context, ball).position;
+        let angle = (position - PIVOT).y.atan2((position - PIVOT).x);
+        assert!((angle + 0.3).abs() < 0.02, "resting at {}", angle);
+    }
+
+    #[test]
+    fn motor_reaches_its_speed() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let center = Vec2f::new(7.5, 5.);
+        let axle = app_context.new_entity_shaped(
+            center,
+            Vec2f::new(0.1, 0.1),
+            WHITE,
+            Shape::Rectangle,
+            RigidBody::Static,
+        );
+        let wheel = app_context.new_entity_shaped(
+            center,
+            Vec2f::new(0.5, 0.5),
+            WHITE,
+            Shape::Circle,
+            RigidBody::Dynamic,
+        );
+        // The axle sits inside the wheel, so they must not push each other apart
+        app_context
+            .entity_manager
+            .components
+            .remove::<ColliderComponent>(&axle);
+        let joint = app_context.add_revolute_joint(axle, wheel, center).unwrap();
+        app_context
+            .entity_manager
+            .joints
+            .get_mut(&joint)
+            .unwrap()
+            .motor = Some(Motor {
+            speed: 4.,
+            max_force: 50.,
+        });
+        let mut engine = engine();
+        for _ in 0..120 {
+            engine.update(&mut app_context, engine.fixed_dt);
+        }
+        let body = app_context
+            .entity_manager
+            .components
+            .get::<PhysicsBody>(&wheel)
+            .unwrap();
+        assert!(
+            (body.angular_velocity - 4.).abs() < 0.05,
+            "{}",
+            body.angular_velocity
+        );
+        // Sags a little under its weight
+        assert!((transform(&app_context, wheel).position - center).length() < 0.02);
+    }
+
+    #[test]
+    fn breaking_raises_an_event() {
+        let (mut app_context, pivot, ball, joint) = pendulum();
+        // Less than the weight of the ball
+        app_context
+            .entity_manager
+            .joints
+            .get_mut(&joint)
+            .unwrap()
+            .break_force = Some(0.05);
+        let mut app = App::from_context((), app_context);
+        app.physics_engine = engine();
+        for _ in 0..30 {
+            app.step(app.physics_engine.fixed_dt);
+        }
+        assert!(app.app_context.entity_manager.joints.is_empty());
+        let broken = app
+            .app_context
+            .events
+            .iter()
+            .filter(|event| matches!(event, Event::JointBroken { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(broken.len(), 1);
+        let Event::JointBroken { id, a, b } = *broken[0];
+        assert_eq!((id, a, b), (joint, pivot, ball));
+    }
+}
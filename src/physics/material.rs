@@ -0,0 +1,203 @@
+use std::fs;
+
+pub const MATERIALS_PATH: &str = "assets/materials.txt";
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    // kg/m^2, the world is 2D so mass comes from area
+    pub density: f32,
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl Material {
+    pub fn new(name: &str, density: f32, restitution: f32, friction: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            density,
+            restitution,
+            friction,
+        }
+    }
+
+    // Whether the material can be used and written to the library file. The name is one word
+    // so the file can be split on whitespace
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty()
+            || self.name.starts_with('#')
+            || self.name.contains(char::is_whitespace)
+        {
+            return Err(format!(
+                "{:?}: names must be one word not starting with #",
+                self.name
+            ));
+        }
+        if !self.density.is_finite() || self.density <= 0. {
+            return Err(format!(
+                "{}: density must be above 0, got {}",
+                self.name, self.density
+            ));
+        }
+        for (property, value) in [
+            ("restitution", self.restitution),
+            ("friction", self.friction),
+        ] {
+            if !(0. ..=1.).contains(&value) {
+                return Err(format!(
+                    "{}: {} must be between 0 and 1, got {}",
+                    self.name, property, value
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::new("default", 1., 0.3, 0.5)
+    }
+}
+
+// Named materials, stored one per line as `name density restitution friction`.
+// Lines starting with # are comments
+pub struct MaterialLibrary {
+    pub materials: Vec<Material>,
+}
+
+impl MaterialLibrary {
+    pub fn defaults() -> Self {
+        Self {
+            materials: vec![
+                Material::default(),
+                Material::new("rubber", 1.1, 0.85, 0.9),
+                Material::new("steel", 7.8, 0.5, 0.4),
+                Material::new("wood", 0.6, 0.4, 0.5),
+                Material::new("ice", 0.92, 0.1, 0.02),
+            ],
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut materials = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            if fields.len() != 4 {
+                return Err(format!(
+                    "line {}: expected `name density restitution friction`",
+                    i + 1
+                ));
+            }
+            let number = |field: &str| {
+                field
+                    .parse::<f32>()
+                    .map_err(|e| format!("line {}: {}: {}", i + 1, field, e))
+            };
+            let material = Material::new(
+                fields[0],
+                number(fields[1])?,
+                number(fields[2])?,
+                number(fields[3])?,
+            );
+            material
+                .validate()
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            materials.push(material);
+        }
+        Ok(Self { materials })
+    }
+
+    // The library in the format `parse` reads. Fails on a material it could not read back
+    pub fn format(&self) -> Result<String, String> {
+        let mut contents = String::from("# name density restitution friction\n");
+        for m in &self.materials {
+            m.validate()?;
+            contents += &format!(
+                "{} {} {} {}\n",
+                m.name, m.density, m.restitution, m.friction
+            );
+        }
+        Ok(contents)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = self.format()?;
+        fs::write(path, contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_libraries_load_back() {
+        let mut library = MaterialLibrary::defaults();
+        library.materials.push(Material::new("foam", 0.05, 0., 1.));
+        let path = std::env::temp_dir().join(format!("materials-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        library.save(path).unwrap();
+        let loaded = MaterialLibrary::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.materials.len(), library.materials.len());
+        for (a, b) in loaded.materials.iter().zip(&library.materials) {
+            assert_eq!(
+                (&a.name, a.density, a.restitution, a.friction),
+                (&b.name, b.density, b.restitution, b.friction)
+            );
+        }
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let library = MaterialLibrary::parse("# a comment\n\n  steel 7.8 0.5 0.4  \n").unwrap();
+        assert_eq!(library.materials.len(), 1);
+        assert_eq!(library.get("steel").unwrap().density, 7.8);
+    }
+
+    #[test]
+    fn bad_lines_are_rejected_with_their_number() {
+        for line in [
+            "steel 7.8 0.5",
+            "steel heavy 0.5 0.4",
+            "steel 0 0.5 0.4",
+            "steel -1 0.5 0.4",
+            "steel NaN 0.5 0.4",
+            "steel inf 0.5 0.4",
+            "steel 7.8 1.5 0.4",
+            "steel 7.8 0.5 -0.1",
+            "steel 7.8 NaN 0.4",
+        ] {
+            let error = MaterialLibrary::parse(&format!("# header\nwood 0.6 0.4 0.5\n{}\n", line))
+                .err()
+                .unwrap_or_else(|| panic!("{} was accepted", line));
+            assert!(error.starts_with("line 3:"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn names_that_do_not_read_back_are_not_saved() {
+        for name in ["two words", "", "#hash", "tab\there"] {
+            let library = MaterialLibrary {
+                materials: vec![Material::new(name, 1., 0.5, 0.5)],
+            };
+            assert!(library.format().is_err(), "{:?} was saved", name);
+        }
+    }
+}
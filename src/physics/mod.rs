@@ -1,3 +1,11 @@
+pub mod boundary;
 pub mod collisions;
+pub mod diagnostics;
+pub mod electrostatics;
 pub mod entities;
+pub mod force_field;
+pub mod history;
+pub mod integrator;
+pub mod joint;
+pub mod material;
 pub mod physics_engine;
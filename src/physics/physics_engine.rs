@@ -1,37 +1,177 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 use macroquad::time::get_frame_time;
+use rayon::{
+    ThreadPool, ThreadPoolBuilder,
+    iter::{
+        IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+    },
+};
 
 use crate::{
-    app::{self, AppContext},
+    app::{AppContext, Event},
     math::math::Vec2f,
-    physics::entities::physics_body::RigidBody,
-    renderer::entity::{Entity, EntityId},
+    physics::{
+        boundary::{Boundary, BoundaryMode},
+        collisions::{
+            island,
+            sat::{self, Collider},
+            solver::{self, Contact, ContactPoint},
+        },
+        diagnostics::Diagnostics,
+        electrostatics::Electrostatics,
+        entities::physics_body::PhysicsBody,
+        history::Snapshot,
+        integrator::Integrator,
+    },
+    renderer::{
+        component::{ColliderComponent, ComponentManager, Storage, TransformComponent},
+        entity::{Entity, EntityId, EntityManager},
+    },
 };
 
-pub struct PhysicsEngine {}
+pub struct PhysicsEngine {
+    pub integrator: Integrator,
+    pub electrostatics: Electrostatics,
+    // Iterate and solve in id order and step by `fixed_dt`, so the same scene and inputs
+    // give bit-identical state on every run and for any thread count
+    pub deterministic: bool,
+    pub fixed_dt: f32,
+    // Simulated seconds per second, below 1 is slow motion
+    pub time_scale: f32,
+    // Frame time not yet simulated in deterministic mode
+    accumulator: f32,
+    steps: u64,
+    time: f32,
+    thread_pool: ThreadPool,
+    diagnostics: Diagnostics,
+}
 
-const GRAVITY_CONST: f32 = 9.81;
-const GRAVITY_DIR: Vec2f = Vec2f { x: 0., y: -1. };
+pub const GRAVITY_CONST: f32 = 9.81;
+pub const GRAVITY_DIR: Vec2f = Vec2f { x: 0., y: -1. };
+// Fixed steps taken in one frame at most, so a slow frame does not snowball
+const MAX_FRAME_STEPS: usize = 8;
 
 impl PhysicsEngine {
     pub fn init() -> Self {
-        Self {}
+        // 0 lets rayon pick one thread per core
+        Self::with_threads(0)
+    }
+
+    pub fn with_threads(threads: usize) -> Self {
+        Self {
+            integrator: Integrator::SemiImplicitEuler,
+            electrostatics: Electrostatics::init(),
+            deterministic: false,
+            fixed_dt: 1. / 60.,
+            time_scale: 1.,
+            accumulator: 0.,
+            steps: 0,
+            time: 0.,
+            thread_pool: Self::build_pool(threads),
+            diagnostics: Diagnostics::zero(),
+        }
+    }
+
+    fn build_pool(threads: usize) -> ThreadPool {
+        ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("physics-{}", i))
+            .build()
+            .expect("Failed to build physics thread pool")
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.thread_pool = Self::build_pool(threads);
+    }
+
+    pub fn threads(&self) -> usize {
+        self.thread_pool.current_num_threads()
+    }
+
+    // Time step for the next update
+    pub fn dt(&self) -> f32 {
+        if self.deterministic {
+            self.fixed_dt
+        } else {
+            get_frame_time()
+        }
+    }
+
+    // Splits a frame of `dt` seconds into steps for the time scale. Slow motion shortens the
+    // step and fast forward takes several, so no step is ever longer than `dt`
+    pub fn substeps(&self, dt: f32) -> (usize, f32) {
+        let steps = self.time_scale.ceil().max(1.) as usize;
+        (steps, dt * self.time_scale / steps as f32)
+    }
+
+    // Steps to take for a rendered frame of `frame_time` seconds. Deterministic mode banks the
+    // scaled frame time and pays it out in whole `fixed_dt` steps, so it keeps real time
+    pub fn frame_steps(&mut self, frame_time: f32) -> (usize, f32) {
+        if !self.deterministic {
+            return self.substeps(frame_time);
+        }
+        self.accumulator += frame_time * self.time_scale;
+        let steps = (self.accumulator / self.fixed_dt) as usize;
+        self.accumulator -= steps as f32 * self.fixed_dt;
+        if steps > MAX_FRAME_STEPS {
+            self.accumulator = 0.;
+        }
+        (steps.min(MAX_FRAME_STEPS), self.fixed_dt)
+    }
+
+    // Number of updates since the engine was created
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // Simulated seconds since the engine was created
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // Energy and momentum after the last step
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
     }
 
     // Sort and sweep broad phase
-    fn broad(entities: &HashMap<EntityId, Entity>) -> Vec<(EntityId, EntityId)> {
-        let mut sorted = entities
+    fn broad(
+        colliders: &Storage<ColliderComponent>,
+        deterministic: bool,
+        boundary: &Boundary,
+        dimensions: Vec2f,
+    ) -> Vec<(EntityId, EntityId)> {
+        let mut sorted = colliders
             .iter()
-            .map(|(id, e)| {
-                (
-                    e.bounding_box.x - e.bounding_box.w / 2.,
-                    e.bounding_box.x + e.bounding_box.w / 2.,
-                    id,
-                )
+            .map(|(id, collider)| {
+                let bb = collider.bounding_box;
+                (bb.x - bb.w / 2., bb.x + bb.w / 2., id)
             })
             .collect::<Vec<(f32, f32, &EntityId)>>();
-        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let periodic = boundary.mode == BoundaryMode::Periodic;
+        if periodic {
+            // Ghost copies of intervals crossing a seam, shifted to the other side
+            let ghosts = sorted
+                .iter()
+                .filter_map(|&(min_x, max_x, id)| {
+                    if max_x > dimensions.x {
+                        Some((min_x - dimensions.x, max_x - dimensions.x, id))
+                    } else if min_x < 0. {
+                        Some((min_x + dimensions.x, max_x + dimensions.x, id))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<(f32, f32, &EntityId)>>();
+            sorted.extend(ghosts);
+        }
+        if deterministic {
+            // Break ties by id so map order never leaks into the pair order
+            sorted.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.2.cmp(b.2)));
+        } else {
+            sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
 
         let mut potential_collisions: Vec<(EntityId, EntityId)> = vec![];
         let mut active_collisions: Vec<(f32, EntityId)> = vec![];
@@ -46,97 +186,542 @@ impl PhysicsEngine {
             active_collisions.push((*max_x_a, **id_a));
         }
 
+        if periodic {
+            // A body can meet its own ghost, or another body twice
+            let mut seen = HashSet::new();
+            potential_collisions.retain(|&(a, b)| a != b && seen.insert((a.min(b), a.max(b))));
+        }
+
         potential_collisions
     }
 
     fn narrow(
-        pairs: &Vec<(EntityId, EntityId)>,
-        entities: &HashMap<EntityId, Entity>,
-    ) -> Vec<(EntityId, EntityId)> {
+        pairs: &[(EntityId, EntityId)],
+        components: &ComponentManager,
+        boundary: &Boundary,
+        dimensions: Vec2f,
+    ) -> Vec<Contact> {
         pairs
-            .iter()
-            .filter(|(a, b)| {
-                let a = entities.get(&a).unwrap();
-                let b = entities.get(&b).unwrap();
+            .par_iter()
+            .filter_map(|&(id_a, id_b)| {
+                let (transform_a, a) = Self::placed_collider(components, &id_a)?;
+                let (transform_b, b) = Self::placed_collider(components, &id_b)?;
+
+                // Test against the nearest image of b
+                let s_a = transform_a.position;
+                let s_b = s_a + boundary.delta(s_a, transform_b.position, dimensions);
+                let shift = s_b - transform_b.position;
+                let mut b_box = b.bounding_box;
+                b_box.x += shift.x;
+                b_box.y += shift.y;
+                if !b_box.intersects(&a.bounding_box) {
+                    return None;
+                }
 
-                b.bounding_box.intersects(&a.bounding_box)
+                // Compound bodies touch through every pair of parts that overlap
+                let colliders_a = Collider::from_component(a, s_a, transform_a.rotation);
+                let colliders_b = Collider::from_component(b, s_b, transform_b.rotation);
+                let mut contacts = vec![];
+                for collider_a in &colliders_a {
+                    for collider_b in &colliders_b {
+                        let Some(manifold) = sat::collide(collider_a, collider_b) else {
+                            continue;
+                        };
+                        contacts.push(Contact {
+                            a: id_a,
+                            b: id_b,
+                            normal: manifold.normal,
+                            points: manifold
+                                .points
+                                .into_iter()
+                                .map(|(point, depth)| {
+                                    ContactPoint::new(point - s_a, point - s_b, depth)
+                                })
+                                .collect(),
+                            restitution: a.restitution.min(b.restitution),
+                            friction: (a.friction * b.friction).sqrt(),
+                        });
+                    }
+                }
+                Some(contacts)
             })
-            .cloned()
+            .flatten()
             .collect()
     }
 
-    fn handle_collision(a: &EntityId, b: &EntityId, entities: &mut HashMap<EntityId, Entity>) {
-        let ae = entities.get(&a).unwrap();
-        let be = entities.get(&b).unwrap();
+    // Colliders without a transform are not anywhere yet
+    fn placed_collider<'a>(
+        components: &'a ComponentManager,
+        id: &EntityId,
+    ) -> Option<(&'a TransformComponent, &'a ColliderComponent)> {
+        Some((components.get(id)?, components.get(id)?))
+    }
+
+    // `field_force` is the force that depends on where the body is, from force fields and
+    // charges. The integrator evaluates it at each of its stages, the accumulated forces are
+    // held constant over the step
+    fn integrate(
+        integrator: Integrator,
+        transform: &mut TransformComponent,
+        p_body: &mut PhysicsBody,
+        dt: f32,
+        field_force: impl Fn(Vec2f) -> Vec2f,
+    ) {
+        if !p_body.is_dynamic() {
+            return;
+        }
 
-        let v_a = ae.physics_body.velocity;
-        let v_b = be.physics_body.velocity;
+        // G = mg = ma, so gravity is independent of mass
+        let g = GRAVITY_DIR * GRAVITY_CONST;
+        let inv_mass = p_body.inv_mass;
+        let a_ext = p_body.force_accumulator * inv_mass;
 
-        let s_a = ae.physics_body.position;
-        let s_b = be.physics_body.position;
+        let (position, velocity, acceleration) =
+            integrator.step(transform.position, p_body.velocity, dt, |x, _| {
+                g + a_ext + field_force(x) * inv_mass
+            });
+        transform.position = position;
+        p_body.velocity = velocity;
+        p_body.acceleration = acceleration;
 
-        let a_invm = ae.physics_body.inv_mass;
-        let b_invm = be.physics_body.inv_mass;
+        // a = t/I
+        let alpha = p_body.torque_accumulator * p_body.inv_inertia;
+        let (rotation, angular_velocity, _) =
+            integrator.step(transform.rotation, p_body.angular_velocity, dt, |_, _| {
+                alpha
+            });
+        transform.rotation = rotation;
+        p_body.angular_velocity = angular_velocity;
 
-        let m_a = ae.physics_body.mass;
-        let m_b = be.physics_body.mass;
-        // J = F*dt = ma*dt = m/(v*dt)*dt = m*dv => dv = J/m
-        let cor = ae.physics_body.cor.min(be.physics_body.cor);
-        let v_rel = v_a - v_b;
-        let mut n = (s_b - s_a).norm();
-        if n.dot(&v_rel) > 0.0 {
-            n = -n; // Ensure normal opposes relative velocity
-        }
+        p_body.force_accumulator = Vec2f::zero();
+        p_body.torque_accumulator = 0.;
+    }
+
+    pub fn update(&mut self, app_context: &mut AppContext, dt: f32) {
+        self.thread_pool.install(|| {
+            self.step(
+                &mut app_context.entity_manager,
+                &mut app_context.events,
+                &app_context.boundary,
+                app_context.physics_dimensions,
+                self.time,
+                dt,
+            )
+        });
+
+        self.diagnostics = Diagnostics::compute(&app_context.entity_manager);
+        self.steps += 1;
+        self.time += dt;
+        app_context.time = self.time;
+    }
 
-        // How much of the relative velocity is in the direction of n
-        let j = -(1. + cor) * v_rel.dot(&n) / (a_invm + b_invm);
-        if a_invm > 0.0 && m_a > 0. {
-            let v_an = v_a + a_invm * j * n;
-            entities.get_mut(&a).unwrap().physics_body.velocity = v_an;
+    // The live world and clock, to be recorded in a History
+    pub fn snapshot(&self, app_context: &AppContext) -> Snapshot {
+        Snapshot {
+            entity_manager: app_context.entity_manager.clone(),
+            steps: self.steps,
+            time: self.time,
         }
-        if b_invm > 0.0 && m_b > 0. {
-            let v_bn = v_b - b_invm * j * n;
-            entities.get_mut(&b).unwrap().physics_body.velocity = v_bn;
+    }
+
+    // Puts the world and clock back to where they were when `snapshot` was taken
+    pub fn restore(&mut self, app_context: &mut AppContext, snapshot: &Snapshot) {
+        app_context.entity_manager.restore(&snapshot.entity_manager);
+        app_context.events.clear();
+        self.steps = snapshot.steps;
+        self.time = snapshot.time;
+        app_context.time = self.time;
+        self.diagnostics = Diagnostics::compute(&app_context.entity_manager);
+    }
+
+    // Where `body` goes over the next `steps` fixed steps. It moves through a scratch copy of
+    // the static geometry, force fields and point charges, so the live world is left alone
+    pub fn predict(&self, app_context: &AppContext, body: Entity, steps: usize) -> Vec<Vec2f> {
+        let mut world = app_context.entity_manager.clone();
+        let dynamic = world
+            .components
+            .storage::<PhysicsBody>()
+            .into_iter()
+            .flatten()
+            .filter(|(_, body)| body.is_dynamic())
+            .map(|(id, _)| *id)
+            .collect::<Vec<EntityId>>();
+        for id in dynamic {
+            world.remove(&id);
         }
+        world.joints.clear();
+        world.mouse_joint = None;
+        let id = world.add(body);
+        let position = |world: &EntityManager| {
+            world
+                .components
+                .get::<TransformComponent>(&id)
+                .unwrap()
+                .position
+        };
+
+        let mut events = vec![];
+        let mut path = vec![position(&world)];
+        let (boundary, dimensions) = (app_context.boundary, app_context.physics_dimensions);
+        self.thread_pool.install(|| {
+            for step in 0..steps {
+                let time = self.time + step as f32 * self.fixed_dt;
+                self.step(
+                    &mut world,
+                    &mut events,
+                    &boundary,
+                    dimensions,
+                    time,
+                    self.fixed_dt,
+                );
+                path.push(position(&world));
+            }
+        });
+        path
     }
-    pub fn update(&self, app_context: &mut AppContext) {
-        let dt = get_frame_time();
+
+    fn step(
+        &self,
+        entity_manager: &mut EntityManager,
+        events: &mut Vec<Event>,
+        boundary: &Boundary,
+        dimensions: Vec2f,
+        time: f32,
+        dt: f32,
+    ) {
+        let components = &mut entity_manager.components;
+        let joints = &mut entity_manager.joints;
+        let mouse_joint = entity_manager.mouse_joint;
+        let force_fields = &entity_manager.force_fields;
+        let point_charges = &entity_manager.point_charges;
+
+        let charges = self.electrostatics.charges(components, point_charges);
 
         // 1. FIRST: Integrate forces and update positions
-        for (_, entity) in &mut app_context.entity_manager.entities {
-            if entity.rigidbody == RigidBody::Static || entity.physics_body.mass <= 0. {
-                continue;
+        components
+            .query::<(
+                &mut TransformComponent,
+                &mut PhysicsBody,
+                Option<&mut ColliderComponent>,
+            )>()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .for_each(|(id, (transform, body, collider))| {
+                if !body.is_dynamic() {
+                    return;
+                }
+                if let Some(joint) = mouse_joint.filter(|joint| joint.body == id) {
+                    joint.apply(transform, body, dt);
+                }
+                body.apply_timed_forces(transform, dt);
+                let charge = body.charge;
+                let field_force = |position: Vec2f| {
+                    let mut force = Vec2f::zero();
+                    for field in force_fields.values() {
+                        force += field.force_at(position, time);
+                    }
+                    if let Some(charges) = &charges
+                        && charge != 0.
+                    {
+                        force += charges.force_on(id, charge, position);
+                    }
+                    force
+                };
+                Self::integrate(self.integrator, transform, body, dt, field_force);
+                boundary.apply(transform, body, collider.as_deref(), dimensions);
+                if let Some(collider) = collider {
+                    collider.update_bounding_box(transform);
+                }
+            });
+
+        // 2. THEN: Detect and resolve collisions
+        let mut possible_collision_pairs = match components.storage::<ColliderComponent>() {
+            Some(colliders) => Self::broad(colliders, self.deterministic, boundary, dimensions),
+            None => vec![],
+        };
+        // Jointed bodies pass through each other
+        let connected: HashSet<_> = joints
+            .values()
+            .map(|joint| (joint.a.min(joint.b), joint.a.max(joint.b)))
+            .collect();
+        possible_collision_pairs.retain(|&(a, b)| !connected.contains(&(a.min(b), a.max(b))));
+        let contacts = Self::narrow(&possible_collision_pairs, components, boundary, dimensions);
+
+        // 3. Solve every island of touching bodies independently
+        let mut islands = island::build_islands(contacts, joints, components, self.deterministic);
+        islands.par_iter_mut().for_each(|island| {
+            solver::solve(
+                &mut island.contacts,
+                &mut island.joints,
+                &mut island.bodies,
+                dt,
+            );
+        });
+        for (id, joint) in island::write_back(islands, components) {
+            if joint.is_broken() {
+                joints.remove(&id);
+                events.push(Event::JointBroken {
+                    id,
+                    a: joint.a,
+                    b: joint.b,
+                });
+            } else {
+                joints.insert(id, joint);
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::color::WHITE;
 
-            // G = mg = ma
-            let G = GRAVITY_DIR * GRAVITY_CONST * entity.physics_body.mass;
-            entity.physics_body.force_accumulator += G;
+    use super::{GRAVITY_CONST, PhysicsEngine};
+    use crate::{
+        app::AppContext,
+        math::math::Vec2f,
+        physics::{
+            boundary::BoundaryMode,
+            electrostatics::COULOMB_CONST,
+            entities::physics_body::{PhysicsBody, RigidBody},
+            integrator::Integrator,
+        },
+        renderer::{
+            component::{ColliderComponent, RenderComponent, TransformComponent},
+            entity::{Entity, Shape},
+        },
+    };
 
-            let (inv_mass, forces) = (
-                entity.physics_body.inv_mass,
-                entity.physics_body.force_accumulator,
+    // A floor with a pile of boxes and balls dropped onto it
+    fn scene() -> AppContext {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        app_context.new_entity_shaped(
+            Vec2f::new(7.5, 0.5),
+            Vec2f::new(15., 1.),
+            WHITE,
+            Shape::Rectangle,
+            RigidBody::Static,
+        );
+        for i in 0..24 {
+            let position = Vec2f::new(
+                3. + (i % 6) as f32 * 1.1 + (i / 6) as f32 * 0.2,
+                2. + (i / 6) as f32,
             );
+            let shape = if i % 2 == 0 {
+                Shape::Circle
+            } else {
+                Shape::Rectangle
+            };
+            let size = if i % 2 == 0 {
+                Vec2f::new(0.3, 0.3)
+            } else {
+                Vec2f::new(0.6, 0.4)
+            };
+            app_context.new_entity_shaped(position, size, WHITE, shape, RigidBody::Dynamic);
+        }
+        app_context
+    }
 
-            let p_body = &mut entity.physics_body;
+    fn run(threads: usize, steps: usize) -> u64 {
+        let mut app_context = scene();
+        let mut engine = PhysicsEngine::with_threads(threads);
+        engine.deterministic = true;
+        for _ in 0..steps {
+            engine.update(&mut app_context, engine.fixed_dt);
+        }
+        app_context.entity_manager.state_hash()
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_result() {
+        assert_eq!(run(1, 240), run(8, 240));
+        // The pile did move, so the hashes are not equal by accident
+        assert_ne!(run(1, 0), run(1, 240));
+    }
 
-            let a = forces * inv_mass;
-            p_body.velocity += a * dt;
-            p_body.position += p_body.velocity * dt;
-            entity.bounding_box.x = p_body.position.x;
-            entity.bounding_box.y = p_body.position.y;
+    #[test]
+    fn same_scene_gives_same_result() {
+        // Each run builds its own scene and engine
+        assert_eq!(run(4, 300), run(4, 300));
+    }
 
-            p_body.force_accumulator = Vec2f::zero();
+    #[test]
+    fn deterministic_frames_keep_real_time() {
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        let fixed_dt = engine.fixed_dt;
+        // A 30 Hz frame takes two 60 Hz steps
+        assert_eq!(engine.frame_steps(2. * fixed_dt + 1e-4), (2, fixed_dt));
+        // A 120 Hz frame steps every other frame
+        assert_eq!(engine.frame_steps(0.5 * fixed_dt).0, 0);
+        assert_eq!(engine.frame_steps(0.5 * fixed_dt).0, 1);
+        // Slow motion stretches the same steps over more frames
+        engine.time_scale = 0.25;
+        let steps = (0..8)
+            .map(|_| engine.frame_steps(fixed_dt).0)
+            .sum::<usize>();
+        assert_eq!(steps, 2);
+        // A stall is not caught up all at once
+        engine.time_scale = 1.;
+        assert_eq!(engine.frame_steps(1.).0, super::MAX_FRAME_STEPS);
+        assert_eq!(engine.frame_steps(0.).0, 0);
+    }
+
+    #[test]
+    fn render_only_entities_are_not_integrated() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let position = Vec2f::new(4., 6.);
+        let decoration = app_context.entity_manager.add(Entity {
+            render: Some(RenderComponent {
+                shape: Shape::Circle,
+                size: Vec2f::new(0.2, 0.2),
+                color: WHITE,
+            }),
+            ..Entity::at(position)
+        });
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        for _ in 0..60 {
+            engine.update(&mut app_context, engine.fixed_dt);
         }
+        let components = &app_context.entity_manager.components;
+        let transform = components.get::<TransformComponent>(&decoration).unwrap();
+        assert_eq!((transform.position, transform.rotation), (position, 0.));
+        assert!(components.get::<PhysicsBody>(&decoration).is_none());
+    }
 
-        // 2. THEN: Detect and resolve collisions
-        let possible_collision_pairs = Self::broad(&app_context.entity_manager.entities);
-        let collision_pairs = Self::narrow(
-            &possible_collision_pairs,
-            &app_context.entity_manager.entities, // Note: not &mut
+    #[test]
+    fn collider_only_entities_are_scenery() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let position = Vec2f::new(7.5, 3.);
+        let transform = TransformComponent::new(position);
+        let collider = ColliderComponent::new(
+            Shape::Rectangle,
+            Vec2f::new(4., 0.5),
+            &app_context.materials.materials[0],
+            &transform,
         );
+        let ledge = app_context.entity_manager.add(Entity {
+            collider: Some(collider),
+            ..Entity::at(position)
+        });
+        let ball = app_context.new_entity_shaped(
+            Vec2f::new(7.5, 6.),
+            Vec2f::new(0.3, 0.3),
+            WHITE,
+            Shape::Circle,
+            RigidBody::Dynamic,
+        );
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        for _ in 0..240 {
+            engine.update(&mut app_context, engine.fixed_dt);
+        }
+        let components = &app_context.entity_manager.components;
+        // The ball landed and stayed on top of the ledge, which did not move
+        let resting = components
+            .get::<TransformComponent>(&ball)
+            .unwrap()
+            .position;
+        assert!((resting.y - 3.55).abs() < 0.05, "ball at {:?}", resting);
+        let transform = components.get::<TransformComponent>(&ledge).unwrap();
+        assert_eq!((transform.position, transform.rotation), (position, 0.));
+    }
+
+    #[test]
+    fn bodies_without_colliders_fall_inside_the_walls() {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        app_context.boundary.mode = BoundaryMode::Walls;
+        let mut body = PhysicsBody::new(1., RigidBody::Dynamic);
+        body.velocity = Vec2f::new(-3., 0.);
+        let point = app_context.entity_manager.add(Entity {
+            body: Some(body),
+            ..Entity::at(Vec2f::new(1., 4.))
+        });
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        let mut fell = false;
+        for _ in 0..240 {
+            engine.update(&mut app_context, engine.fixed_dt);
+            let components = &app_context.entity_manager.components;
+            let position = components
+                .get::<TransformComponent>(&point)
+                .unwrap()
+                .position;
+            // Kept in by its center, as it has no shape
+            assert!(
+                position.x >= 0. && position.y >= 0.,
+                "left at {:?}",
+                position
+            );
+            fell |= position.y < 2.;
+        }
+        assert!(fell);
+    }
 
-        for pair in &collision_pairs {
-            Self::handle_collision(&pair.0, &pair.1, &mut app_context.entity_manager.entities);
+    // A charged ball circling an opposite point charge. Returns the largest change of total
+    // energy over the run, relative to the starting energy
+    fn orbit_drift(integrator: Integrator) -> f32 {
+        const RADIUS: f32 = 1.5;
+        // Centripetal acceleration, large enough next to gravity to keep the orbit round
+        const PULL: f32 = 100. * GRAVITY_CONST;
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let center = Vec2f::new(7.5, 5.);
+        let ball = app_context.new_entity_shaped(
+            center + Vec2f::new(RADIUS, 0.),
+            Vec2f::new(0.1, 0.1),
+            macroquad::color::WHITE,
+            Shape::Circle,
+            RigidBody::Dynamic,
+        );
+        let body = app_context
+            .entity_manager
+            .components
+            .get_mut::<PhysicsBody>(&ball)
+            .unwrap();
+        let (mass, q) = (body.mass, 1e-4);
+        body.charge = q;
+        body.velocity = Vec2f::new(0., (PULL * RADIUS).sqrt());
+        let big_q = -mass * PULL * RADIUS * RADIUS / (COULOMB_CONST * q);
+        app_context.add_point_charge(center, big_q);
+
+        let mut engine = PhysicsEngine::with_threads(1);
+        engine.deterministic = true;
+        engine.integrator = integrator;
+        engine.electrostatics.enabled = true;
+        // About 60 steps per orbit
+        engine.fixed_dt = 1. / 240.;
+
+        let energy = |app_context: &AppContext| {
+            let components = &app_context.entity_manager.components;
+            let position = components
+                .get::<TransformComponent>(&ball)
+                .unwrap()
+                .position;
+            let velocity = components.get::<PhysicsBody>(&ball).unwrap().velocity;
+            // Softened like the force, see electrostatics
+            let r = position - center;
+            let distance = (r.dot(&r) + 0.05 * 0.05).sqrt();
+            0.5 * mass * velocity.dot(&velocity)
+                + mass * GRAVITY_CONST * position.y
+                + COULOMB_CONST * q * big_q / distance
+        };
+        let start = energy(&app_context);
+        let mut drift: f32 = 0.;
+        for _ in 0..240 {
+            engine.update(&mut app_context, engine.fixed_dt);
+            drift = drift.max((energy(&app_context) - start).abs());
         }
+        drift / start.abs()
+    }
+
+    #[test]
+    fn integrators_drift_differently() {
+        let euler = orbit_drift(Integrator::SemiImplicitEuler);
+        let verlet = orbit_drift(Integrator::VelocityVerlet);
+        let rk4 = orbit_drift(Integrator::Rk4);
+        // Roughly an order of magnitude apart each
+        assert!(euler > 5. * verlet, "euler {} verlet {}", euler, verlet);
+        assert!(verlet > 5. * rk4, "verlet {} rk4 {}", verlet, rk4);
     }
 }
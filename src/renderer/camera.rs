@@ -0,0 +1,76 @@
+use crate::{math::math::Vec2f, renderer::entity::EntityId};
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 20.;
+
+// Maps the world (meters, y up) into a viewport on the screen (pixels, y down) with the same
+// scale on both axes. At zoom 1 the whole world fits and the rest of the viewport is
+// letterboxed
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    // World point shown at the middle of the viewport
+    pub center: Vec2f,
+    pub zoom: f32,
+    // Kept in the middle of the view, cleared when the camera is panned by hand
+    pub follow: Option<EntityId>,
+    // Size of the world that fits the viewport at zoom 1
+    pub world: Vec2f,
+    // Top left corner and size of the screen area drawn into, in pixels
+    pub viewport_min: Vec2f,
+    pub viewport_size: Vec2f,
+}
+
+impl Camera {
+    pub fn new(world: Vec2f) -> Self {
+        Self {
+            center: world / 2.,
+            zoom: 1.,
+            follow: None,
+            world,
+            viewport_min: Vec2f::zero(),
+            viewport_size: Vec2f::new(1., 1.),
+        }
+    }
+
+    // Shows the whole world again
+    pub fn reset(&mut self) {
+        self.center = self.world / 2.;
+        self.zoom = 1.;
+        self.follow = None;
+    }
+
+    pub fn set_viewport(&mut self, min: Vec2f, size: Vec2f) {
+        self.viewport_min = min;
+        self.viewport_size = Vec2f::new(size.x.max(1.), size.y.max(1.));
+    }
+
+    // Pixels per meter
+    pub fn scale(&self) -> f32 {
+        (self.viewport_size.x / self.world.x).min(self.viewport_size.y / self.world.y) * self.zoom
+    }
+
+    pub fn to_screen(&self, position: Vec2f) -> Vec2f {
+        let middle = self.viewport_min + self.viewport_size / 2.;
+        let offset = (position - self.center) * self.scale();
+        Vec2f::new(middle.x + offset.x, middle.y - offset.y)
+    }
+
+    pub fn to_world(&self, pixels: Vec2f) -> Vec2f {
+        let middle = self.viewport_min + self.viewport_size / 2.;
+        let offset = Vec2f::new(pixels.x - middle.x, middle.y - pixels.y) / self.scale();
+        self.center + offset
+    }
+
+    // Moves the view with a drag of `pixels` on the screen
+    pub fn pan(&mut self, pixels: Vec2f) {
+        self.center -= Vec2f::new(pixels.x, -pixels.y) / self.scale();
+        self.follow = None;
+    }
+
+    // Zooms in by `factor` keeping the world point under `pixels` where it is
+    pub fn zoom_at(&mut self, pixels: Vec2f, factor: f32) {
+        let before = self.to_world(pixels);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center += before - self.to_world(pixels);
+    }
+}
@@ -1,19 +1,119 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, btree_map},
+    iter::Peekable,
 };
 
-pub struct Position {
-    pub x: f32,
-    pub y: f32,
-}
+use macroquad::color::Color;
+
+use crate::{
+    math::math::Vec2f,
+    physics::{entities::physics_body::BoundingBox, material::Material},
+    renderer::entity::{EntityId, Shape},
+};
 
+// Where an entity is. Entities with a body are placed at its center of mass
+#[derive(Clone, Copy, Debug)]
 pub struct TransformComponent {
-    pub position: Position,
+    pub position: Vec2f,
+    pub rotation: f32,
+}
+
+impl TransformComponent {
+    pub fn new(position: Vec2f) -> Self {
+        Self {
+            position,
+            rotation: 0.,
+        }
+    }
+}
+
+// The shape other entities bump into. Without a body it is fixed scenery
+#[derive(Clone, Debug)]
+pub struct ColliderComponent {
+    pub shape: Shape,
+    pub size: Vec2f,
+    pub bounding_box: BoundingBox,
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl ColliderComponent {
+    // Polygons are turned counter-clockwise so their normals point out. They are not
+    // recentered, so scenery without a body stays where it was placed
+    pub fn new(
+        mut shape: Shape,
+        size: Vec2f,
+        material: &Material,
+        transform: &TransformComponent,
+    ) -> Self {
+        shape.orient();
+        let mut collider = Self {
+            shape,
+            size,
+            bounding_box: BoundingBox::new(0., 0., 0., 0.),
+            restitution: material.restitution,
+            friction: material.friction,
+        };
+        collider.update_bounding_box(transform);
+        collider
+    }
+
+    // Keeps the bounding box around the shape as the entity moves and rotates
+    pub fn update_bounding_box(&mut self, transform: &TransformComponent) {
+        let (min, max) = self.shape.bounds(self.size, transform.rotation);
+        let center = transform.position + (min + max) / 2.;
+        self.bounding_box = BoundingBox::new(center.x, center.y, max.x - min.x, max.y - min.y);
+    }
+}
+
+// How an entity is drawn, independent of what it collides as
+#[derive(Clone, Debug)]
+pub struct RenderComponent {
+    pub shape: Shape,
+    pub size: Vec2f,
+    pub color: Color,
+}
+
+// Anything that can be attached to an entity. Clone so the world can be snapshotted,
+// Send and Sync so it can be handed to the physics threads
+pub trait Component: Clone + Send + Sync + 'static {}
+
+impl<T: Clone + Send + Sync + 'static> Component for T {}
+
+// Every component of one type, in entity order
+pub type Storage<T> = BTreeMap<EntityId, T>;
+
+// A Storage<T> with its type erased
+pub trait AnyStorage: Any + Send + Sync {
+    fn remove_entity(&mut self, id: &EntityId);
+    fn clone_box(&self) -> Box<dyn AnyStorage>;
+}
+
+impl<T: Component> AnyStorage for Storage<T> {
+    fn remove_entity(&mut self, id: &EntityId) {
+        self.remove(id);
+    }
+
+    fn clone_box(&self) -> Box<dyn AnyStorage> {
+        Box::new(self.clone())
+    }
 }
 
 pub struct ComponentManager {
-    pub storages: HashMap<TypeId, Box<dyn Any>>,
+    pub storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl Clone for ComponentManager {
+    fn clone(&self) -> Self {
+        Self {
+            storages: self
+                .storages
+                .iter()
+                .map(|(type_id, storage)| (*type_id, storage.clone_box()))
+                .collect(),
+        }
+    }
 }
 
 impl ComponentManager {
@@ -22,4 +122,353 @@ impl ComponentManager {
             storages: HashMap::new(),
         }
     }
+
+    pub fn storage<T: Component>(&self) -> Option<&Storage<T>> {
+        let storage: &dyn Any = self.storages.get(&TypeId::of::<T>())?.as_ref();
+        storage.downcast_ref()
+    }
+
+    pub fn storage_mut<T: Component>(&mut self) -> Option<&mut Storage<T>> {
+        let storage: &mut dyn Any = self.storages.get_mut(&TypeId::of::<T>())?.as_mut();
+        storage.downcast_mut()
+    }
+
+    // Returns the component the entity already had of this type
+    pub fn insert<T: Component>(&mut self, id: EntityId, component: T) -> Option<T> {
+        let storage: &mut dyn Any = self
+            .storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T>::new()))
+            .as_mut();
+        storage
+            .downcast_mut::<Storage<T>>()
+            .expect("Storage registered under the wrong type")
+            .insert(id, component)
+    }
+
+    pub fn get<T: Component>(&self, id: &EntityId) -> Option<&T> {
+        self.storage::<T>()?.get(id)
+    }
+
+    pub fn get_mut<T: Component>(&mut self, id: &EntityId) -> Option<&mut T> {
+        self.storage_mut::<T>()?.get_mut(id)
+    }
+
+    pub fn remove<T: Component>(&mut self, id: &EntityId) -> Option<T> {
+        self.storage_mut::<T>()?.remove(id)
+    }
+
+    // Drops every component of the entity
+    pub fn remove_entity(&mut self, id: &EntityId) {
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.storages.clear();
+    }
+
+    // The entities that have every component in Q, in id order. Q is a tuple of &T, &mut T,
+    // Option<&T> and Option<&mut T>, e.g. query::<(&Transform, Option<&mut Velocity>)>().
+    // Options match entities without the component too, but at least one part must not be an
+    // Option. Panics if a type appears twice
+    pub fn query<Q: Query>(&mut self) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+        Q::iter(&mut self.storages)
+    }
+}
+
+fn downcast<'a, T: Component>(storage: &'a mut (dyn AnyStorage + 'static)) -> &'a mut Storage<T> {
+    let storage: &mut dyn Any = storage;
+    storage
+        .downcast_mut()
+        .expect("Storage registered under the wrong type")
+}
+
+// A storage being walked in id order, None if nothing has the component
+type Column<I> = Option<Peekable<I>>;
+
+// Skips the entities before `id` and returns the one the column stopped at
+fn seek<'a, V>(
+    column: &mut Column<impl Iterator<Item = (&'a EntityId, V)>>,
+    id: EntityId,
+) -> Option<EntityId> {
+    let column = column.as_mut()?;
+    while *column.peek()?.0 < id {
+        column.next();
+    }
+    Some(*column.peek()?.0)
+}
+
+// The component of `id`, if the column stopped at it
+fn take<'a, V>(
+    column: &mut Column<impl Iterator<Item = (&'a EntityId, V)>>,
+    id: EntityId,
+) -> Option<V> {
+    let column = column.as_mut()?;
+    if *column.peek()?.0 != id {
+        return None;
+    }
+    column.next().map(|(_, component)| component)
+}
+
+// &T, &mut T, Option<&T> or Option<&mut T> in a query
+pub trait Fetch {
+    type Component: Component;
+    type Item<'a>;
+    type Column<'a>;
+    // Whether entities without the component still match
+    const OPTIONAL: bool;
+
+    fn column<'a>(storage: Option<&'a mut (dyn AnyStorage + 'static)>) -> Self::Column<'a>;
+    // The first entity at or after `id` this can match, None once there are no more
+    fn seek(column: &mut Self::Column<'_>, id: EntityId) -> Option<EntityId>;
+    // Called with the id seek returned once every part of the query agrees on it
+    fn take<'a>(column: &mut Self::Column<'a>, id: EntityId) -> Self::Item<'a>;
+}
+
+impl<T: Component> Fetch for &T {
+    type Component = T;
+    type Item<'a> = &'a T;
+    type Column<'a> = Column<btree_map::Iter<'a, EntityId, T>>;
+    const OPTIONAL: bool = false;
+
+    fn column<'a>(storage: Option<&'a mut (dyn AnyStorage + 'static)>) -> Self::Column<'a> {
+        storage.map(|storage| downcast::<T>(storage).iter().peekable())
+    }
+
+    fn seek(column: &mut Self::Column<'_>, id: EntityId) -> Option<EntityId> {
+        seek(column, id)
+    }
+
+    fn take<'a>(column: &mut Self::Column<'a>, id: EntityId) -> &'a T {
+        take(column, id).expect("Query took an entity the column did not stop at")
+    }
+}
+
+impl<T: Component> Fetch for &mut T {
+    type Component = T;
+    type Item<'a> = &'a mut T;
+    type Column<'a> = Column<btree_map::IterMut<'a, EntityId, T>>;
+    const OPTIONAL: bool = false;
+
+    fn column<'a>(storage: Option<&'a mut (dyn AnyStorage + 'static)>) -> Self::Column<'a> {
+        storage.map(|storage| downcast::<T>(storage).iter_mut().peekable())
+    }
+
+    fn seek(column: &mut Self::Column<'_>, id: EntityId) -> Option<EntityId> {
+        seek(column, id)
+    }
+
+    fn take<'a>(column: &mut Self::Column<'a>, id: EntityId) -> &'a mut T {
+        take(column, id).expect("Query took an entity the column did not stop at")
+    }
+}
+
+impl<T: Component> Fetch for Option<&T> {
+    type Component = T;
+    type Item<'a> = Option<&'a T>;
+    type Column<'a> = Column<btree_map::Iter<'a, EntityId, T>>;
+    const OPTIONAL: bool = true;
+
+    fn column<'a>(storage: Option<&'a mut (dyn AnyStorage + 'static)>) -> Self::Column<'a> {
+        storage.map(|storage| downcast::<T>(storage).iter().peekable())
+    }
+
+    fn seek(column: &mut Self::Column<'_>, id: EntityId) -> Option<EntityId> {
+        seek(column, id);
+        Some(id)
+    }
+
+    fn take<'a>(column: &mut Self::Column<'a>, id: EntityId) -> Option<&'a T> {
+        take(column, id)
+    }
+}
+
+impl<T: Component> Fetch for Option<&mut T> {
+    type Component = T;
+    type Item<'a> = Option<&'a mut T>;
+    type Column<'a> = Column<btree_map::IterMut<'a, EntityId, T>>;
+    const OPTIONAL: bool = true;
+
+    fn column<'a>(storage: Option<&'a mut (dyn AnyStorage + 'static)>) -> Self::Column<'a> {
+        storage.map(|storage| downcast::<T>(storage).iter_mut().peekable())
+    }
+
+    fn seek(column: &mut Self::Column<'_>, id: EntityId) -> Option<EntityId> {
+        seek(column, id);
+        Some(id)
+    }
+
+    fn take<'a>(column: &mut Self::Column<'a>, id: EntityId) -> Option<&'a mut T> {
+        take(column, id)
+    }
+}
+
+// A tuple of Fetch types
+pub trait Query {
+    type Item<'a>;
+
+    fn iter(
+        storages: &mut HashMap<TypeId, Box<dyn AnyStorage>>,
+    ) -> impl Iterator<Item = (EntityId, Self::Item<'_>)>;
+}
+
+// The id right after `id` in storage order
+fn after(id: EntityId) -> EntityId {
+    match id.generation.checked_add(1) {
+        Some(generation) => EntityId {
+            index: id.index,
+            generation,
+        },
+        None => EntityId {
+            index: id.index + 1,
+            generation: 0,
+        },
+    }
+}
+
+// The storages are all sorted by id, so they are walked side by side and an entity is
+// yielded once every one of them has reached it
+macro_rules! impl_query {
+    ($(($fetch:ident, $column:ident)),+) => {
+        impl<$($fetch: Fetch),+> Query for ($($fetch,)+) {
+            type Item<'a> = ($($fetch::Item<'a>,)+);
+
+            fn iter(
+                storages: &mut HashMap<TypeId, Box<dyn AnyStorage>>,
+            ) -> impl Iterator<Item = (EntityId, Self::Item<'_>)> {
+                assert!(
+                    ![$($fetch::OPTIONAL),+].into_iter().all(|optional| optional),
+                    "A query needs at least one component that is not optional"
+                );
+                let [$($column),+] = storages
+                    .get_disjoint_mut([$(&TypeId::of::<$fetch::Component>()),+]);
+                $(let mut $column = $fetch::column($column.map(|storage| &mut **storage));)+
+                let mut id = EntityId { index: 0, generation: 0 };
+                std::iter::from_fn(move || loop {
+                    let next = [$($fetch::seek(&mut $column, id)?),+].into_iter().max()?;
+                    if next == id {
+                        id = after(next);
+                        return Some((next, ($($fetch::take(&mut $column, next),)+)));
+                    }
+                    id = next;
+                })
+            }
+        }
+    };
+}
+
+impl_query!((A, a));
+impl_query!((A, a), (B, b));
+impl_query!((A, a), (B, b), (C, c));
+impl_query!((A, a), (B, b), (C, c), (D, d));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Position(f32);
+    #[derive(Clone, Debug, PartialEq)]
+    struct Velocity(f32);
+    #[derive(Clone, Debug, PartialEq)]
+    struct Frozen;
+    #[derive(Clone, Debug, PartialEq)]
+    struct Mass;
+
+    fn id(index: usize) -> EntityId {
+        EntityId {
+            index,
+            generation: 0,
+        }
+    }
+
+    // Positions on 0..6, velocities only on the odd ones and 4, frozen on 2 and 3
+    fn world() -> ComponentManager {
+        let mut components = ComponentManager::init();
+        for index in 0..6 {
+            components.insert(id(index), Position(index as f32));
+        }
+        for index in [1, 3, 4, 5] {
+            components.insert(id(index), Velocity(10. * index as f32));
+        }
+        components.insert(id(2), Frozen);
+        components.insert(id(3), Frozen);
+        components
+    }
+
+    #[test]
+    fn storages_with_gaps_are_joined() {
+        let mut components = world();
+        let ids = components
+            .query::<(&Position, &Velocity)>()
+            .map(|(id, (position, velocity))| {
+                assert_eq!(velocity.0, 10. * position.0);
+                id.index
+            })
+            .collect::<Vec<usize>>();
+        assert_eq!(ids, [1, 3, 4, 5]);
+        let ids = components
+            .query::<(&Velocity, &Frozen, &Position)>()
+            .map(|(id, _)| id.index)
+            .collect::<Vec<usize>>();
+        assert_eq!(ids, [3]);
+    }
+
+    #[test]
+    fn missing_storage_matches_nothing() {
+        let mut components = world();
+        // Nothing was ever given a mass, so there is no storage for it
+        assert!(components.storage::<Mass>().is_none());
+        assert_eq!(components.query::<(&Position, &Mass)>().count(), 0);
+        assert_eq!(components.query::<(Option<&Mass>, &Frozen)>().count(), 2);
+        let mut empty = ComponentManager::init();
+        assert_eq!(empty.query::<(&Position,)>().count(), 0);
+        assert_eq!(empty.query::<(&Position, Option<&Velocity>)>().count(), 0);
+    }
+
+    #[test]
+    fn writes_are_kept() {
+        let mut components = world();
+        for (_, (position, velocity)) in components.query::<(&mut Position, &Velocity)>() {
+            position.0 += velocity.0;
+        }
+        for (_, (velocity, _)) in components.query::<(Option<&mut Velocity>, &Frozen)>() {
+            if let Some(velocity) = velocity {
+                velocity.0 = 0.;
+            }
+        }
+        assert_eq!(components.get::<Position>(&id(0)), Some(&Position(0.)));
+        assert_eq!(components.get::<Position>(&id(5)), Some(&Position(55.)));
+        assert_eq!(components.get::<Velocity>(&id(3)), Some(&Velocity(0.)));
+        assert_eq!(components.get::<Velocity>(&id(4)), Some(&Velocity(40.)));
+    }
+
+    #[test]
+    fn options_match_entities_without_the_component() {
+        let mut components = world();
+        let rows = components
+            .query::<(&Position, Option<&Velocity>)>()
+            .map(|(id, (_, velocity))| (id.index, velocity.is_some()))
+            .collect::<Vec<(usize, bool)>>();
+        assert_eq!(
+            rows,
+            [
+                (0, false),
+                (1, true),
+                (2, false),
+                (3, true),
+                (4, true),
+                (5, true)
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_types_panic() {
+        let mut components = world();
+        components.query::<(&Position, &mut Position)>().count();
+    }
 }
@@ -1,180 +1,911 @@
-use std::{any, collections::HashMap};
+use std::collections::{BTreeMap, BTreeSet};
 
 use macroquad::{
-    color::{Color, GREEN, PURPLE, RED},
-    miniquad::gl::GL_RENDERBUFFER,
-    shapes::{draw_circle, draw_line, draw_rectangle, draw_rectangle_lines},
-    window::{screen_height, screen_width},
+    color::{BLUE, Color, GREEN, ORANGE, PURPLE, RED, SKYBLUE, YELLOW},
+    math::vec2,
+    shapes::{
+        DrawRectangleParams, draw_circle, draw_circle_lines, draw_line, draw_rectangle_ex,
+        draw_rectangle_lines, draw_triangle,
+    },
 };
 
 use crate::{
-    math::math::Vec2f,
-    physics::entities::physics_body::{BoundingBox, PhysicsBody, RigidBody},
+    math::{math::Vec2f, polygon},
+    physics::{
+        collisions::sat::Collider,
+        electrostatics::{Electrostatics, PointCharge},
+        entities::physics_body::PhysicsBody,
+        force_field::{ForceField, ForceFieldId},
+        joint::{Joint, JointId, MouseJoint},
+        material::Material,
+    },
+    renderer::{
+        camera::Camera,
+        component::{
+            ColliderComponent, Component, ComponentManager, RenderComponent, TransformComponent,
+        },
+    },
 };
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+// Dots rather than a line, so a path that wraps around the world stays clean
+pub fn draw_trajectory(path: &[Vec2f], camera: &Camera, color: Color) {
+    for point in path.iter().step_by(2) {
+        let pixels = camera.to_screen(*point);
+        draw_circle(pixels.x, pixels.y, 2., color);
+    }
+}
+
+fn draw_arrow(from: Vec2f, to: Vec2f, thickness: f32, color: Color) {
+    draw_line(from.x, from.y, to.x, to.y, thickness, color);
+    let length = (to - from).length();
+    if length < 1e-3 {
+        return;
+    }
+    let back = (from - to) / length * (length * 0.3).min(8.);
+    let side = Vec2f::new(-back.y, back.x) * 0.5;
+    for head in [to + back + side, to + back - side] {
+        draw_line(to.x, to.y, head.x, head.y, thickness, color);
+    }
+}
+
+fn draw_shape(
+    shape: &Shape,
+    size: Vec2f,
+    position: Vec2f,
+    rotation: f32,
+    color: Color,
+    camera: &Camera,
+) {
+    let pixels_per_meter = camera.scale();
+    let pixel_coords = camera.to_screen(position);
+    match shape {
+        Shape::Circle => {
+            draw_circle(
+                pixel_coords.x,
+                pixel_coords.y,
+                size.x * pixels_per_meter,
+                color,
+            );
+        }
+        Shape::Rectangle => {
+            // Screen y points down, so the rotation is mirrored
+            draw_rectangle_ex(
+                pixel_coords.x,
+                pixel_coords.y,
+                size.x * pixels_per_meter,
+                size.y * pixels_per_meter,
+                DrawRectangleParams {
+                    offset: vec2(0.5, 0.5),
+                    rotation: -rotation,
+                    color,
+                },
+            );
+        }
+        Shape::Polygon(_) | Shape::Triangle(_) => {
+            let points = shape
+                .vertices(size)
+                .iter()
+                .map(|v| {
+                    let p = camera.to_screen(position + v.rotate(rotation));
+                    vec2(p.x, p.y)
+                })
+                .collect::<Vec<_>>();
+            // Convex, so a fan from the first vertex covers it
+            for i in 1..points.len().saturating_sub(1) {
+                draw_triangle(points[0], points[i], points[i + 1], color);
+            }
+        }
+        Shape::Capsule { radius, length } => {
+            draw_rectangle_ex(
+                pixel_coords.x,
+                pixel_coords.y,
+                length * pixels_per_meter,
+                2. * radius * pixels_per_meter,
+                DrawRectangleParams {
+                    offset: vec2(0.5, 0.5),
+                    rotation: -rotation,
+                    color,
+                },
+            );
+            for end in shape.vertices(size) {
+                let end = camera.to_screen(position + end.rotate(rotation));
+                draw_circle(end.x, end.y, radius * pixels_per_meter, color);
+            }
+        }
+        Shape::Chain { vertices, .. } => {
+            for pair in vertices.windows(2) {
+                let a = camera.to_screen(position + pair[0].rotate(rotation));
+                let b = camera.to_screen(position + pair[1].rotate(rotation));
+                draw_line(a.x, a.y, b.x, b.y, 3., color);
+            }
+        }
+        Shape::Compound(parts) => {
+            for part in parts {
+                draw_shape(
+                    &part.shape,
+                    part.size,
+                    position + part.offset.rotate(rotation),
+                    rotation,
+                    color,
+                    camera,
+                );
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
 pub enum Shape {
     Circle,
     Rectangle,
+    // Convex, vertices relative to the entity position
+    Polygon(Vec<Vec2f>),
+    Triangle([Vec2f; 3]),
+    // Segment of `length` along the local x axis, swept by a circle of `radius`
+    Capsule {
+        radius: f32,
+        length: f32,
+    },
+    // Several shapes moving as one body
+    Compound(Vec<ShapePart>),
+    // Connected segments for static terrain. One sided chains only collide from the
+    // left of the direction the vertices run in, so ground drawn left to right is solid from above
+    Chain {
+        vertices: Vec<Vec2f>,
+        one_sided: bool,
+    },
 }
 
-pub struct Entity {
+// One shape of a compound body, placed relative to the entity position
+#[derive(PartialEq, Clone, Debug)]
+pub struct ShapePart {
+    pub offset: Vec2f,
     pub size: Vec2f,
-    pub color: Color,
-    pub physics_body: PhysicsBody,
-    pub bounding_box: BoundingBox,
-    pub rigidbody: RigidBody,
     pub shape: Shape,
 }
 
-impl Entity {
-    pub fn new(
-        size: Vec2f,
-        color: Color,
-        physics_body: PhysicsBody,
-        shape: Shape,
-        rigidbody: RigidBody,
-    ) -> Self {
-        let bb = match shape {
-            Shape::Circle => {
-                // Circle is rendered at center position
-                BoundingBox::new(
-                    physics_body.position.x.clone(),
-                    physics_body.position.y.clone(),
-                    size.x * 2.,
-                    size.y * 2.,
-                )
+impl Shape {
+    pub fn area(&self, size: Vec2f) -> f32 {
+        match self {
+            Shape::Circle => std::f32::consts::PI * size.x * size.x,
+            Shape::Rectangle => size.x * size.y,
+            Shape::Polygon(vertices) => polygon::area(vertices),
+            Shape::Triangle(vertices) => polygon::area(vertices),
+            Shape::Capsule { radius, length } => {
+                2. * radius * length + std::f32::consts::PI * radius * radius
             }
-            Shape::Rectangle => {
-                // Rectangle is rendered with center offset, so bounding box center is at position
-                BoundingBox::new(
-                    physics_body.position.x.clone(),
-                    physics_body.position.y.clone(),
-                    size.x,
-                    size.y,
-                )
+            Shape::Compound(parts) => parts.iter().map(|part| part.shape.area(part.size)).sum(),
+            Shape::Chain { .. } => 0.,
+        }
+    }
+
+    // Terrain through `vertices`, checked so no segment has zero length
+    pub fn chain(vertices: Vec<Vec2f>, one_sided: bool) -> Result<Shape, String> {
+        polygon::validate_chain(&vertices)?;
+        Ok(Shape::Chain {
+            vertices,
+            one_sided,
+        })
+    }
+
+    // A single segment of terrain
+    pub fn edge(a: Vec2f, b: Vec2f, one_sided: bool) -> Result<Shape, String> {
+        Shape::chain(vec![a, b], one_sided)
+    }
+
+    // Any simple outline, split into convex parts when it is concave. Outlines with fewer
+    // than three corners, no area or crossing edges are refused
+    pub fn concave(outline: Vec<Vec2f>) -> Result<Shape, String> {
+        let mut pieces = polygon::decompose(&outline)?;
+        if pieces.len() == 1 {
+            return Ok(Shape::Polygon(pieces.remove(0)));
+        }
+        Ok(Shape::Compound(
+            pieces
+                .into_iter()
+                .map(|piece| ShapePart {
+                    offset: Vec2f::zero(),
+                    size: Vec2f::zero(),
+                    shape: Shape::Polygon(piece),
+                })
+                .collect(),
+        ))
+    }
+
+    // Orders polygon vertices counter-clockwise without moving the shape
+    pub fn orient(&mut self) {
+        match self {
+            Shape::Polygon(vertices) => polygon::orient(vertices),
+            Shape::Triangle(vertices) => polygon::orient(vertices),
+            Shape::Compound(parts) => {
+                for part in parts {
+                    part.shape.orient();
+                }
             }
-        };
-        Self {
-            size,
-            color,
-            physics_body,
-            bounding_box: bb,
-            rigidbody,
-            shape,
+            _ => {}
         }
     }
 
-    fn render(&self, physics_dimensions: Vec2f, debug: bool, forces: bool, com: bool) {
-        let mut pixel_coords = self.physics_body.position;
-        pixel_coords.x = pixel_coords.x / physics_dimensions.x * screen_width();
-        pixel_coords.y =
-            screen_height() - (pixel_coords.y / physics_dimensions.y * screen_height());
+    // Moves the shape so its center of mass is at the origin and returns how far it moved
+    pub fn normalize(&mut self) -> Vec2f {
+        match self {
+            Shape::Polygon(vertices) => polygon::normalize(vertices),
+            Shape::Triangle(vertices) => polygon::normalize(vertices),
+            Shape::Compound(parts) => {
+                let mut center = Vec2f::zero();
+                let mut area = 0.;
+                for part in parts.iter_mut() {
+                    part.offset += part.shape.normalize();
+                    let part_area = part.shape.area(part.size);
+                    center += part.offset * part_area;
+                    area += part_area;
+                }
+                if area <= 0. {
+                    return Vec2f::zero();
+                }
+                let center = center / area;
+                for part in parts.iter_mut() {
+                    part.offset -= center;
+                }
+                center
+            }
+            _ => Vec2f::zero(),
+        }
+    }
 
-        let pixel_size = Vec2f::new(
-            self.size.x * (screen_width() / physics_dimensions.x),
-            self.size.y * (screen_height() / physics_dimensions.y),
-        );
+    // Moment of inertia around the origin, for a normalized shape
+    pub fn inertia(&self, size: Vec2f, mass: f32) -> f32 {
+        match self {
+            // I = 1/2 m r^2
+            Shape::Circle => 0.5 * mass * size.x * size.x,
+            // I = 1/12 m (w^2 + h^2)
+            Shape::Rectangle => mass * (size.x * size.x + size.y * size.y) / 12.,
+            Shape::Polygon(vertices) => polygon::inertia(vertices, mass),
+            Shape::Triangle(vertices) => polygon::inertia(vertices, mass),
+            // A box between two half discs, each moved out to its end of the segment
+            Shape::Capsule { radius, length } => {
+                let box_mass = mass * 2. * radius * length / self.area(size);
+                let disc_mass = mass - box_mass;
+                box_mass * (length * length + 4. * radius * radius) / 12.
+                    + disc_mass
+                        * (radius * radius / 2.
+                            + length * length / 4.
+                            + 4. * radius * length / (3. * std::f32::consts::PI))
+            }
+            // Mass is shared out by area, then each part is moved by the parallel axis theorem
+            Shape::Compound(parts) => {
+                let area = self.area(size);
+                if area <= 0. {
+                    return 0.;
+                }
+                parts
+                    .iter()
+                    .map(|part| {
+                        let part_mass = mass * part.shape.area(part.size) / area;
+                        part.shape.inertia(part.size, part_mass)
+                            + part_mass * part.offset.dot(&part.offset)
+                    })
+                    .sum()
+            }
+            Shape::Chain { .. } => 0.,
+        }
+    }
 
-        let bb_size = Vec2f::new(
-            self.bounding_box.w * (screen_width() / physics_dimensions.x),
-            self.bounding_box.h * (screen_height() / physics_dimensions.y),
+    // Corners of the box around the shape when rotated by `rotation`
+    pub fn bounds(&self, size: Vec2f, rotation: f32) -> (Vec2f, Vec2f) {
+        let (mut min, mut max) = (
+            Vec2f::new(f32::MAX, f32::MAX),
+            Vec2f::new(f32::MIN, f32::MIN),
         );
-        match self.shape {
-            Shape::Circle => {
-                draw_circle(pixel_coords.x, pixel_coords.y, pixel_size.x, self.color);
-                if debug {
-                    draw_rectangle_lines(
-                        pixel_coords.x - pixel_size.x,
-                        pixel_coords.y - pixel_size.y,
-                        bb_size.x,
-                        bb_size.y,
-                        2.,
-                        GREEN,
-                    );
+        let mut extend = |lo: Vec2f, hi: Vec2f| {
+            min = Vec2f::new(min.x.min(lo.x), min.y.min(lo.y));
+            max = Vec2f::new(max.x.max(hi.x), max.y.max(hi.y));
+        };
+        match self {
+            Shape::Circle => extend(-size, size),
+            Shape::Compound(parts) => {
+                for part in parts {
+                    let offset = part.offset.rotate(rotation);
+                    let (lo, hi) = part.shape.bounds(part.size, rotation);
+                    extend(offset + lo, offset + hi);
                 }
             }
-            Shape::Rectangle => {
-                draw_rectangle(
-                    pixel_coords.x - pixel_size.x / 2.,
-                    pixel_coords.y - pixel_size.y / 2.,
-                    pixel_size.x,
-                    pixel_size.y,
-                    self.color,
-                );
-                if debug {
-                    draw_rectangle_lines(
-                        pixel_coords.x - pixel_size.x / 2.,
-                        pixel_coords.y - pixel_size.y / 2.,
-                        bb_size.x,
-                        bb_size.y,
-                        2.,
-                        GREEN,
-                    );
+            _ => {
+                let radius = match self {
+                    Shape::Capsule { radius, .. } => *radius,
+                    _ => 0.,
+                };
+                let r = Vec2f::new(radius, radius);
+                for v in self.vertices(size) {
+                    let v = v.rotate(rotation);
+                    extend(v - r, v + r);
                 }
             }
         }
+        (min, max)
+    }
+
+    // A convex polygon, checked so the collision code never sees a degenerate one
+    pub fn polygon(vertices: Vec<Vec2f>) -> Result<Shape, String> {
+        polygon::validate(&vertices)?;
+        Ok(Shape::Polygon(vertices))
+    }
+
+    pub fn regular_polygon(sides: usize, radius: f32) -> Result<Shape, String> {
+        Shape::polygon(polygon::regular(sides, radius))
+    }
+
+    // Equilateral, with its corners `radius` from the center
+    pub fn triangle(radius: f32) -> Result<Shape, String> {
+        let v = polygon::regular(3, radius);
+        polygon::validate(&v)?;
+        Ok(Shape::Triangle([v[0], v[1], v[2]]))
+    }
+
+    // Corners around the center of mass, counter-clockwise and unrotated.
+    // For capsules these are the ends of the segment and for chains the points along it.
+    // Empty for circles and compounds
+    pub fn vertices(&self, size: Vec2f) -> Vec<Vec2f> {
+        match self {
+            Shape::Circle => vec![],
+            Shape::Rectangle => {
+                let (w, h) = (size.x / 2., size.y / 2.);
+                vec![
+                    Vec2f::new(-w, -h),
+                    Vec2f::new(w, -h),
+                    Vec2f::new(w, h),
+                    Vec2f::new(-w, h),
+                ]
+            }
+            Shape::Polygon(vertices) => vertices.clone(),
+            Shape::Triangle(vertices) => vertices.to_vec(),
+            Shape::Capsule { length, .. } => {
+                vec![Vec2f::new(-length / 2., 0.), Vec2f::new(length / 2., 0.)]
+            }
+            Shape::Compound(_) => vec![],
+            Shape::Chain { vertices, .. } => vertices.clone(),
+        }
+    }
+}
+
+// The components an entity is spawned with. Parts left as None are not attached, so a
+// decoration can be render-only and fixed scenery can do without a body
+#[derive(Clone)]
+pub struct Entity {
+    pub transform: TransformComponent,
+    pub collider: Option<ColliderComponent>,
+    pub body: Option<PhysicsBody>,
+    pub render: Option<RenderComponent>,
+}
 
-        if com {
-            draw_circle(pixel_coords.x, pixel_coords.y, 5., PURPLE);
+impl Entity {
+    // Only a place in the world
+    pub fn at(position: Vec2f) -> Self {
+        Self {
+            transform: TransformComponent::new(position),
+            collider: None,
+            body: None,
+            render: None,
         }
+    }
 
-        if forces {
-            draw_line(
-                pixel_coords.x,
-                pixel_coords.y,
-                pixel_coords.x - self.physics_body.velocity.x * 10.,
-                pixel_coords.y - self.physics_body.velocity.y * 10.,
-                2.,
-                RED,
-            );
+    // A body drawn and colliding as `shape`
+    pub fn new(
+        position: Vec2f,
+        size: Vec2f,
+        color: Color,
+        shape: Shape,
+        material: &Material,
+        mut physics_body: PhysicsBody,
+    ) -> Self {
+        let mut shape = shape;
+        // The body sits at the center of mass so it rotates around it
+        let transform = TransformComponent::new(position + shape.normalize());
+        let inertia = shape.inertia(size, physics_body.mass);
+        physics_body.set_inertia(inertia);
+
+        Self {
+            transform,
+            collider: Some(ColliderComponent::new(
+                shape.clone(),
+                size,
+                material,
+                &transform,
+            )),
+            body: Some(physics_body),
+            render: Some(RenderComponent { shape, size, color }),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-pub struct EntityId(pub usize);
+// Every component of type T whose entity has a transform to draw it at
+fn placed<T: Component>(
+    components: &ComponentManager,
+) -> impl Iterator<Item = (&TransformComponent, &T)> {
+    components
+        .storage::<T>()
+        .into_iter()
+        .flatten()
+        .filter_map(|(id, component)| Some((components.get(id)?, component)))
+}
+
+// Distance in meters from a force field's center at which it can be grabbed
+pub const FIELD_HANDLE_RADIUS: f32 = 0.2;
 
+// Slots are reused after a remove. The generation goes up every time, so an id kept from
+// before the remove no longer finds anything
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct EntityId {
+    pub index: usize,
+    pub generation: u32,
+}
+
+#[derive(Clone)]
 pub struct EntityManager {
-    pub entities: HashMap<EntityId, Entity>,
-    curr_id: usize,
+    // Every live entity, whatever components it has
+    pub entities: BTreeSet<EntityId>,
+    // Ordered so summing the fields on a body is deterministic
+    pub force_fields: BTreeMap<ForceFieldId, ForceField>,
+    pub point_charges: Vec<PointCharge>,
+    pub joints: BTreeMap<JointId, Joint>,
+    // The body being dragged with the mouse
+    pub mouse_joint: Option<MouseJoint>,
+    pub components: ComponentManager,
+    // Generation of the next id from every slot, and the slots that are free. For a live
+    // entity it is its own generation unless a restore moved it past ids from a dropped future
+    generations: Vec<u32>,
+    free_slots: Vec<usize>,
+    curr_field_id: usize,
+    curr_joint_id: usize,
 }
 
 impl EntityManager {
     pub fn init() -> Self {
         Self {
-            entities: HashMap::new(),
-            curr_id: 0,
+            entities: BTreeSet::new(),
+            force_fields: BTreeMap::new(),
+            point_charges: vec![],
+            joints: BTreeMap::new(),
+            mouse_joint: None,
+            components: ComponentManager::init(),
+            generations: vec![],
+            free_slots: vec![],
+            curr_field_id: 0,
+            curr_joint_id: 0,
         }
     }
 
     fn new_entity_id(&mut self) -> EntityId {
-        let e_id = EntityId(self.curr_id);
-        self.curr_id += 1;
-        e_id
+        let index = self.free_slots.pop().unwrap_or_else(|| {
+            self.generations.push(0);
+            self.generations.len() - 1
+        });
+        EntityId {
+            index,
+            generation: self.generations[index],
+        }
     }
 
-    pub fn add(&mut self, entity: Entity) -> EntityId {
+    // Frees the slot of an entity that was just taken out of `entities`
+    fn free_entity_id(&mut self, id: EntityId) {
+        let generation = &mut self.generations[id.index];
+        *generation = (*generation).max(id.generation + 1);
+        self.free_slots.push(id.index);
+    }
+
+    // Goes back to `snapshot`. Generations only ever go up, so ids handed out since the
+    // snapshot was taken are never given to another entity
+    pub fn restore(&mut self, snapshot: &EntityManager) {
+        let mut generations = snapshot.generations.clone();
+        let slots = generations.len().max(self.generations.len());
+        generations.resize(slots, 0);
+        let mut free_slots = snapshot.free_slots.clone();
+        for (index, &generation) in self.generations.iter().enumerate() {
+            // A live entity has used its generation, a free slot has not
+            let used = self.entities.contains(&EntityId { index, generation });
+            generations[index] = generations[index].max(generation + used as u32);
+            if index >= snapshot.generations.len() {
+                free_slots.push(index);
+            }
+        }
+        *self = EntityManager {
+            generations,
+            free_slots,
+            ..snapshot.clone()
+        };
+    }
+
+    // Also removes its components and the joints attached to it. False if the entity is
+    // already gone
+    pub fn remove(&mut self, id: &EntityId) -> bool {
+        if !self.entities.remove(id) {
+            return false;
+        }
+        self.free_entity_id(*id);
+        self.components.remove_entity(id);
+        self.joints
+            .retain(|_, joint| joint.a != *id && joint.b != *id);
+        if self.mouse_joint.is_some_and(|joint| joint.body == *id) {
+            self.mouse_joint = None;
+        }
+        true
+    }
+
+    // The entity whose collider is under `point`, the newest one if they overlap
+    pub fn entity_at(&self, point: Vec2f) -> Option<EntityId> {
+        let colliders = self.components.storage::<ColliderComponent>()?;
+        colliders
+            .iter()
+            .rev()
+            .filter(|(_, collider)| {
+                let bb = collider.bounding_box;
+                (point.x - bb.x).abs() <= bb.w / 2. && (point.y - bb.y).abs() <= bb.h / 2.
+            })
+            .find(|(id, collider)| {
+                let Some(transform) = self.components.get::<TransformComponent>(id) else {
+                    return false;
+                };
+                Collider::from_component(collider, transform.position, transform.rotation)
+                    .iter()
+                    .any(|part| part.contains(point))
+            })
+            .map(|(id, _)| *id)
+    }
+
+    pub fn is_alive(&self, id: &EntityId) -> bool {
+        self.entities.contains(id)
+    }
+
+    // An entity with no components yet
+    pub fn spawn(&mut self) -> EntityId {
         let entity_id = self.new_entity_id();
-        self.entities.insert(entity_id, entity);
+        self.entities.insert(entity_id);
         entity_id
     }
 
-    pub fn render_all(&self, physics_dimensions: Vec2f, debug: bool, forces: bool, com: bool) {
-        self.entities
-            .iter()
-            .for_each(|(_, e)| e.render(physics_dimensions, debug, forces, com));
+    pub fn add(&mut self, entity: Entity) -> EntityId {
+        let entity_id = self.spawn();
+        self.components.insert(entity_id, entity.transform);
+        if let Some(collider) = entity.collider {
+            self.components.insert(entity_id, collider);
+        }
+        if let Some(body) = entity.body {
+            self.components.insert(entity_id, body);
+        }
+        if let Some(render) = entity.render {
+            self.components.insert(entity_id, render);
+        }
+        entity_id
     }
 
-    pub fn get_entity(&self, id: &EntityId) -> Option<&Entity> {
-        self.entities.get(id)
+    // A copy of the entity's components, None if it is not placed in the world
+    pub fn get_entity(&self, id: &EntityId) -> Option<Entity> {
+        Some(Entity {
+            transform: *self.components.get::<TransformComponent>(id)?,
+            collider: self.components.get::<ColliderComponent>(id).cloned(),
+            body: self.components.get::<PhysicsBody>(id).cloned(),
+            render: self.components.get::<RenderComponent>(id).cloned(),
+        })
     }
 
-    pub fn get_entity_mut(&mut self, id: &EntityId) -> Option<&mut Entity> {
-        self.entities.get_mut(id)
+    pub fn render_all(&self, camera: &Camera, debug: bool, forces: bool, com: bool) {
+        let components = &self.components;
+        for (transform, render) in placed::<RenderComponent>(components) {
+            draw_shape(
+                &render.shape,
+                render.size,
+                transform.position,
+                transform.rotation,
+                render.color,
+                camera,
+            );
+        }
+
+        if debug {
+            for collider in components
+                .storage::<ColliderComponent>()
+                .into_iter()
+                .flat_map(|colliders| colliders.values())
+            {
+                let bb = collider.bounding_box;
+                let bb_size = Vec2f::new(bb.w, bb.h) * camera.scale();
+                let bb_center = camera.to_screen(Vec2f::new(bb.x, bb.y));
+                draw_rectangle_lines(
+                    bb_center.x - bb_size.x / 2.,
+                    bb_center.y - bb_size.y / 2.,
+                    bb_size.x,
+                    bb_size.y,
+                    2.,
+                    GREEN,
+                );
+            }
+        }
+
+        for (transform, body) in placed::<PhysicsBody>(components) {
+            let pixel_coords = camera.to_screen(transform.position);
+            if com {
+                draw_circle(pixel_coords.x, pixel_coords.y, 5., PURPLE);
+            }
+            if forces {
+                draw_line(
+                    pixel_coords.x,
+                    pixel_coords.y,
+                    pixel_coords.x - body.velocity.x * 10.,
+                    pixel_coords.y - body.velocity.y * 10.,
+                    2.,
+                    RED,
+                );
+            }
+        }
+
+        self.render_joints(camera);
+    }
+
+    pub fn add_joint(&mut self, joint: Joint) -> JointId {
+        let joint_id = JointId(self.curr_joint_id);
+        self.curr_joint_id += 1;
+        self.joints.insert(joint_id, joint);
+        joint_id
+    }
+
+    pub fn remove_joint(&mut self, id: &JointId) -> Option<Joint> {
+        self.joints.remove(id)
+    }
+
+    // A line from each body's center to its anchor
+    fn render_joints(&self, camera: &Camera) {
+        for joint in self.joints.values() {
+            let (Some(a), Some(b)) = (
+                self.components.get::<TransformComponent>(&joint.a),
+                self.components.get::<TransformComponent>(&joint.b),
+            ) else {
+                continue;
+            };
+            let (anchor_a, anchor_b) = joint.anchors(a, b);
+            for (transform, anchor) in [(a, anchor_a), (b, anchor_b)] {
+                let from = camera.to_screen(transform.position);
+                let to = camera.to_screen(anchor);
+                draw_line(from.x, from.y, to.x, to.y, 2., ORANGE);
+            }
+            let anchor = camera.to_screen(anchor_a);
+            draw_circle_lines(anchor.x, anchor.y, 4., 2., ORANGE);
+        }
+
+        // The mouse joint as a line from the grabbed point to the cursor
+        if let Some(joint) = &self.mouse_joint
+            && let Some(transform) = self.components.get::<TransformComponent>(&joint.body)
+        {
+            let from = camera.to_screen(joint.anchor(transform));
+            let to = camera.to_screen(joint.target);
+            draw_line(from.x, from.y, to.x, to.y, 1., SKYBLUE);
+            draw_circle(from.x, from.y, 3., SKYBLUE);
+        }
+    }
+
+    pub fn add_force_field(&mut self, field: ForceField) -> ForceFieldId {
+        let field_id = ForceFieldId(self.curr_field_id);
+        self.curr_field_id += 1;
+        self.force_fields.insert(field_id, field);
+        field_id
+    }
+
+    pub fn remove_force_field(&mut self, id: &ForceFieldId) -> Option<ForceField> {
+        self.force_fields.remove(id)
+    }
+
+    // Sum of all force fields at a point
+    pub fn field_force_at(&self, point: Vec2f, time: f32) -> Vec2f {
+        let mut force = Vec2f::zero();
+        for field in self.force_fields.values() {
+            force += field.force_at(point, time);
+        }
+        force
+    }
+
+    // Field centers as drag handles, plus a grid of arrows showing the combined field
+    pub fn render_force_fields(&self, camera: &Camera, forces: bool, time: f32) {
+        for field in self.force_fields.values() {
+            let center = camera.to_screen(field.position);
+            let handle = FIELD_HANDLE_RADIUS * camera.scale();
+            draw_circle_lines(center.x, center.y, handle, 2., SKYBLUE);
+        }
+
+        if !forces || self.force_fields.is_empty() {
+            return;
+        }
+
+        const SPACING: f32 = 0.5;
+        let (columns, rows) = (
+            (camera.world.x / SPACING) as usize,
+            (camera.world.y / SPACING) as usize,
+        );
+        for i in 0..=columns {
+            for j in 0..=rows {
+                let point = Vec2f::new(i as f32 * SPACING, j as f32 * SPACING);
+                let force = self.field_force_at(point, time);
+                let magnitude = force.length();
+                if magnitude < 1e-3 {
+                    continue;
+                }
+                // Long arrows would overlap their neighbours
+                let tip = point + force / magnitude * (magnitude * 0.05).min(SPACING * 0.8);
+                draw_arrow(camera.to_screen(point), camera.to_screen(tip), 1.5, SKYBLUE);
+            }
+        }
+    }
+
+    // Point charges, plus a grid of arrows showing the electric field when `field` is set
+    pub fn render_electric_field(
+        &self,
+        camera: &Camera,
+        field: bool,
+        electrostatics: &Electrostatics,
+    ) {
+        for point_charge in &self.point_charges {
+            let center = camera.to_screen(point_charge.position);
+            let color = if point_charge.charge >= 0. { RED } else { BLUE };
+            draw_circle(center.x, center.y, 6., color);
+        }
+
+        if !field {
+            return;
+        }
+
+        const SPACING: f32 = 0.5;
+        // Field strength that maps to half the maximum arrow length
+        const REFERENCE_FIELD: f32 = 1e4;
+        let (columns, rows) = (
+            (camera.world.x / SPACING) as usize,
+            (camera.world.y / SPACING) as usize,
+        );
+        let points = (0..=columns)
+            .flat_map(|i| (0..=rows).map(move |j| Vec2f::new(i as f32, j as f32) * SPACING))
+            .collect::<Vec<Vec2f>>();
+        let fields = electrostatics.field_at_points(&self.components, &self.point_charges, &points);
+        for (point, e) in points.into_iter().zip(fields) {
+            let magnitude = e.length();
+            if magnitude < 1e-3 {
+                continue;
+            }
+            // Saturating length, the field spans many orders of magnitude near charges
+            let length = SPACING * 0.8 * magnitude / (magnitude + REFERENCE_FIELD);
+            draw_arrow(
+                camera.to_screen(point),
+                camera.to_screen(point + e / magnitude * length),
+                1.5,
+                YELLOW,
+            );
+        }
+    }
+
+    // FNV-1a over the ids and exact bit patterns of every entity's pose and motion, in id
+    // order. Equal hashes across runs mean the simulations stayed bit-identical
+    pub fn state_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        for id in &self.entities {
+            write(&(id.index as u64).to_le_bytes());
+            write(&id.generation.to_le_bytes());
+            let mut values = vec![];
+            if let Some(transform) = self.components.get::<TransformComponent>(id) {
+                values.extend([
+                    transform.position.x,
+                    transform.position.y,
+                    transform.rotation,
+                ]);
+            }
+            if let Some(body) = self.components.get::<PhysicsBody>(id) {
+                values.extend([body.velocity.x, body.velocity.y, body.angular_velocity]);
+            }
+            for value in values {
+                write(&value.to_bits().to_le_bytes());
+            }
+        }
+        hash
     }
 
     pub fn clear(&mut self) {
-        self.entities = HashMap::new();
+        // Sorted so ids are handed out again in the same order on every run
+        for id in std::mem::take(&mut self.entities).into_iter().rev() {
+            self.free_entity_id(id);
+        }
+        self.force_fields = BTreeMap::new();
+        self.point_charges = vec![];
+        self.joints = BTreeMap::new();
+        self.mouse_joint = None;
+        self.components.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn spawn_at(entity_manager: &mut EntityManager, x: f32) -> EntityId {
+        let id = entity_manager.spawn();
+        let transform = TransformComponent::new(Vec2f::new(x, 0.));
+        entity_manager.components.insert(id, transform);
+        id
+    }
+
+    #[test]
+    fn removed_entities_are_gone() {
+        let mut entity_manager = EntityManager::init();
+        let id = spawn_at(&mut entity_manager, 1.);
+        assert!(entity_manager.remove(&id));
+        assert!(!entity_manager.is_alive(&id));
+        assert!(
+            entity_manager
+                .components
+                .get::<TransformComponent>(&id)
+                .is_none()
+        );
+        assert!(!entity_manager.remove(&id));
+    }
+
+    #[test]
+    fn recycled_slots_reject_old_ids() {
+        let mut entity_manager = EntityManager::init();
+        let old = spawn_at(&mut entity_manager, 1.);
+        entity_manager.remove(&old);
+        let new = spawn_at(&mut entity_manager, 2.);
+        assert_eq!(old.index, new.index);
+        assert_ne!(old, new);
+        assert!(!entity_manager.is_alive(&old));
+        assert!(
+            entity_manager
+                .components
+                .get::<TransformComponent>(&old)
+                .is_none()
+        );
+        assert!(!entity_manager.remove(&old));
+        assert!(entity_manager.is_alive(&new));
+    }
+
+    #[test]
+    fn ids_stay_unique_after_restore() {
+        let mut entity_manager = EntityManager::init();
+        let kept = spawn_at(&mut entity_manager, 1.);
+        let snapshot = entity_manager.clone();
+
+        // A future that is thrown away, reusing the slot and taking a new one
+        entity_manager.remove(&kept);
+        let dropped = [
+            spawn_at(&mut entity_manager, 2.),
+            spawn_at(&mut entity_manager, 3.),
+        ];
+        let freed = spawn_at(&mut entity_manager, 4.);
+        entity_manager.remove(&freed);
+
+        entity_manager.restore(&snapshot);
+        assert!(entity_manager.is_alive(&kept));
+        for id in dropped.iter().chain([&freed]) {
+            assert!(!entity_manager.is_alive(id));
+        }
+
+        let mut ids = BTreeSet::from([kept, dropped[0], dropped[1], freed]);
+        for x in 0..4 {
+            let id = spawn_at(&mut entity_manager, x as f32);
+            assert!(ids.insert(id), "{:?} was handed out before", id);
+        }
+        // Freeing the restored entity does not hand its slot back at an old generation
+        entity_manager.remove(&kept);
+        let id = spawn_at(&mut entity_manager, 5.);
+        assert!(ids.insert(id), "{:?} was handed out before", id);
+        for id in dropped {
+            assert!(
+                entity_manager
+                    .components
+                    .get::<TransformComponent>(&id)
+                    .is_none()
+            );
+        }
     }
 }
@@ -1,4 +1,5 @@
 pub mod asset;
+pub mod camera;
 pub mod component;
 pub mod entities;
 pub mod entity;
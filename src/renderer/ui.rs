@@ -1,8 +1,21 @@
-use egui_macroquad::egui::{self, Key, Pos2, Rect};
-use macroquad::input::mouse_position;
-use std::{collections::HashMap, sync::Arc};
+use egui_macroquad::egui;
+use macroquad::{input::mouse_position, time::get_time};
+use std::sync::Arc;
 
-use crate::{app::App, math::math::Vec2f, renderer::entity::Shape};
+use crate::{
+    app::App,
+    math::math::Vec2f,
+    physics::{
+        boundary::BoundaryMode,
+        entities::physics_body::PhysicsBody,
+        force_field::{Falloff, ForceField, ForceFieldKind},
+        integrator::Integrator,
+        joint::{JointKind, Motor},
+        material::{MATERIALS_PATH, MaterialLibrary},
+    },
+    renderer::{component::TransformComponent, entity::Shape},
+    schedule::Stage,
+};
 
 pub struct TextMetadata {
     pub text: String,
@@ -50,6 +63,51 @@ impl UiManager {
                         {
                             app.paused = !app.paused;
                         }
+                        // One tick at a time while paused, also on the period key
+                        if ui
+                            .add_enabled(app.paused, egui::Button::new("Step"))
+                            .clicked()
+                        {
+                            app.step(app.physics_engine.fixed_dt);
+                        }
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut app.physics_engine.time_scale, 0.05..=8.)
+                            .logarithmic(true)
+                            .text("Time scale"),
+                    );
+                    ui.label(format!(
+                        "Simulated time: {:.2} s, wall time: {:.2} s",
+                        app.physics_engine.time(),
+                        get_time()
+                    ));
+                    let history = &mut app.history;
+                    if !history.snapshots.is_empty() {
+                        let last = history.snapshots.len() - 1;
+                        let mut index = history.cursor.unwrap_or(last);
+                        ui.horizontal(|ui| {
+                            ui.label("Timeline:");
+                            // Scrubbing pauses on the chosen snapshot, play resumes from it
+                            let slider = egui::Slider::new(&mut index, 0..=last).show_value(false);
+                            if ui.add(slider).changed()
+                                && let Some(snapshot) = history.seek(index)
+                            {
+                                app.physics_engine.restore(&mut app.app_context, snapshot);
+                                app.paused = true;
+                            }
+                            ui.label(format!("{:.1} s", app.physics_engine.time()));
+                        });
+                    }
+                    // Middle drag pans, the wheel zooms
+                    ui.horizontal(|ui| {
+                        let camera = &mut app.app_context.camera;
+                        ui.label(format!("Zoom: {:.2}x", camera.zoom));
+                        if camera.follow.is_some() && ui.button("Stop following").clicked() {
+                            camera.follow = None;
+                        }
+                        if ui.button("Reset camera").clicked() {
+                            camera.reset();
+                        }
                     });
                     egui::ScrollArea::new([false, true]).show(ui, |ui| {
                         egui::CollapsingHeader::new(format!(
@@ -57,58 +115,528 @@ impl UiManager {
                             app.app_context.entity_manager.entities.len()
                         ))
                         .show(ui, |ui| {
-                            app.app_context
-                                .entity_manager
+                            let mut despawned = None;
+                            let mut followed = None;
+                            let entity_manager = &app.app_context.entity_manager;
+                            let components = &entity_manager.components;
+                            entity_manager
                                 .entities
                                 .iter()
                                 .enumerate()
-                                .for_each(|(i, (_, e))| {
+                                .for_each(|(i, id)| {
                                     egui::CollapsingHeader::new(format!("Entity {}", i)).show(
                                         ui,
                                         |ui| {
-                                            let physics_body = &e.physics_body;
-                                            let position = physics_body.position;
-                                            let velocity = physics_body.velocity;
-                                            let acceleration = physics_body.acceleration;
-                                            ui.label(format!(
-                                                "Position: {:.2}, {:.2}",
-                                                position.x, position.y
-                                            ));
-                                            ui.label(format!(
-                                                "Velocity: {:.2}, {:.2}",
-                                                velocity.x, velocity.y
-                                            ));
-                                            ui.label(format!(
-                                                "Acceleration: {:.2}, {:.2}",
-                                                acceleration.x, acceleration.y
-                                            ));
+                                            if let Some(transform) =
+                                                components.get::<TransformComponent>(id)
+                                            {
+                                                let position = transform.position;
+                                                ui.label(format!(
+                                                    "Position: {:.2}, {:.2}",
+                                                    position.x, position.y
+                                                ));
+                                            }
+                                            if let Some(physics_body) =
+                                                components.get::<PhysicsBody>(id)
+                                            {
+                                                let velocity = physics_body.velocity;
+                                                let acceleration = physics_body.acceleration;
+                                                ui.label(format!(
+                                                    "Velocity: {:.2}, {:.2}",
+                                                    velocity.x, velocity.y
+                                                ));
+                                                ui.label(format!(
+                                                    "Acceleration: {:.2}, {:.2}",
+                                                    acceleration.x, acceleration.y
+                                                ));
+                                                if physics_body.charge != 0. {
+                                                    ui.label(format!(
+                                                        "Charge: {:.2} uC",
+                                                        physics_body.charge * 1e6
+                                                    ));
+                                                }
+                                            }
+                                            ui.horizontal(|ui| {
+                                                if ui.button("Follow").clicked() {
+                                                    followed = Some(*id);
+                                                }
+                                                if ui.button("Despawn").clicked() {
+                                                    despawned = Some(*id);
+                                                }
+                                            });
                                         },
                                     );
                                 });
+                            if let Some(id) = followed {
+                                app.app_context.camera.follow = Some(id);
+                            }
+                            if let Some(id) = despawned {
+                                app.app_context.despawn(id);
+                            }
+                        });
+                        egui::CollapsingHeader::new(format!(
+                            "Force Fields {}",
+                            app.app_context.entity_manager.force_fields.len()
+                        ))
+                        .show(ui, |ui| {
+                            let center = app.app_context.physics_dimensions / 2.;
+                            ui.horizontal_wrapped(|ui| {
+                                let new_field = if ui.button("Wind").clicked() {
+                                    Some(ForceField::wind(center, 5., 2., Vec2f::new(1., 0.)))
+                                } else if ui.button("Vortex").clicked() {
+                                    Some(ForceField::vortex(center, 3., 5.))
+                                } else if ui.button("Attractor").clicked() {
+                                    Some(ForceField::radial(center, 3., 5., Falloff::Linear))
+                                } else if ui.button("Repulsor").clicked() {
+                                    Some(ForceField::radial(center, 3., -5., Falloff::Linear))
+                                } else {
+                                    None
+                                };
+                                if let Some(field) = new_field {
+                                    app.app_context.add_force_field(field);
+                                }
+                            });
+
+                            let mut removed = None;
+                            for (id, field) in
+                                app.app_context.entity_manager.force_fields.iter_mut()
+                            {
+                                egui::CollapsingHeader::new(format!("{} {}", field.name(), id.0))
+                                    .show(ui, |ui| {
+                                        ui.add(
+                                            egui::Slider::new(&mut field.strength, -20.0..=20.)
+                                                .text("Strength"),
+                                        );
+                                        ui.add(
+                                            egui::Slider::new(&mut field.radius, 0.1..=20.)
+                                                .text("Radius"),
+                                        );
+                                        match &mut field.kind {
+                                            ForceFieldKind::Wind {
+                                                direction,
+                                                turbulence,
+                                            } => {
+                                                let mut angle =
+                                                    direction.y.atan2(direction.x).to_degrees();
+                                                if ui
+                                                    .add(
+                                                        egui::Slider::new(
+                                                            &mut angle,
+                                                            -180.0..=180.,
+                                                        )
+                                                        .text("Direction"),
+                                                    )
+                                                    .changed()
+                                                {
+                                                    let (sin, cos) = angle.to_radians().sin_cos();
+                                                    *direction = Vec2f::new(cos, sin);
+                                                }
+                                                ui.add(
+                                                    egui::Slider::new(
+                                                        turbulence,
+                                                        0.0..=std::f32::consts::PI,
+                                                    )
+                                                    .text("Turbulence"),
+                                                );
+                                            }
+                                            ForceFieldKind::Vortex { falloff }
+                                            | ForceFieldKind::Radial { falloff } => {
+                                                ui.horizontal(|ui| {
+                                                    ui.label("Falloff:");
+                                                    ui.radio_value(
+                                                        falloff,
+                                                        Falloff::Constant,
+                                                        "Constant",
+                                                    );
+                                                    ui.radio_value(
+                                                        falloff,
+                                                        Falloff::Linear,
+                                                        "Linear",
+                                                    );
+                                                    ui.radio_value(
+                                                        falloff,
+                                                        Falloff::InverseSquare,
+                                                        "1/r^2",
+                                                    );
+                                                });
+                                            }
+                                        }
+                                        if ui.button("Remove").clicked() {
+                                            removed = Some(*id);
+                                        }
+                                    });
+                            }
+                            if let Some(id) = removed {
+                                app.app_context.entity_manager.remove_force_field(&id);
+                            }
+                        });
+                        egui::CollapsingHeader::new(format!(
+                            "Joints {}",
+                            app.app_context.entity_manager.joints.len()
+                        ))
+                        .show(ui, |ui| {
+                            // Left drag grabs a body, static ones only move while paused
+                            let ctx = &mut app.app_context;
+                            if ui
+                                .add(
+                                    egui::Slider::new(&mut ctx.grab_max_force, 1.0..=5000.)
+                                        .logarithmic(true)
+                                        .text("Mouse max force"),
+                                )
+                                .changed()
+                                && let Some(joint) = &mut ctx.entity_manager.mouse_joint
+                            {
+                                joint.max_force = ctx.grab_max_force;
+                            }
+
+                            let mut removed = None;
+                            for (id, joint) in app.app_context.entity_manager.joints.iter_mut() {
+                                egui::CollapsingHeader::new(format!(
+                                    "{} {}: {} - {}",
+                                    joint.name(),
+                                    id.0,
+                                    joint.a.index,
+                                    joint.b.index
+                                ))
+                                .show(ui, |ui| {
+                                    ui.label(format!(
+                                        "Reaction force: {:.2} N",
+                                        joint.reaction_force
+                                    ));
+                                    let mut motor = joint.motor.is_some();
+                                    if ui.checkbox(&mut motor, "Motor").changed() {
+                                        joint.motor = motor.then_some(Motor {
+                                            speed: 0.,
+                                            max_force: 10.,
+                                        });
+                                    }
+                                    if let Some(motor) = &mut joint.motor {
+                                        ui.add(
+                                            egui::Slider::new(&mut motor.speed, -20.0..=20.)
+                                                .text("Speed"),
+                                        );
+                                        ui.add(
+                                            egui::Slider::new(&mut motor.max_force, 0.0..=100.)
+                                                .text("Max force"),
+                                        );
+                                    }
+                                    // Radians for revolute joints, meters for prismatic ones
+                                    let (range, unit) = match joint.kind {
+                                        JointKind::Revolute => (std::f32::consts::PI, "rad"),
+                                        JointKind::Prismatic { .. } => (5., "m"),
+                                    };
+                                    let mut limited = joint.limits.is_some();
+                                    if ui.checkbox(&mut limited, "Limits").changed() {
+                                        joint.limits = limited.then_some((-range / 4., range / 4.));
+                                    }
+                                    if let Some((lower, upper)) = &mut joint.limits {
+                                        ui.add(
+                                            egui::Slider::new(lower, -range..=range)
+                                                .text(format!("Lower ({})", unit)),
+                                        );
+                                        ui.add(
+                                            egui::Slider::new(upper, -range..=range)
+                                                .text(format!("Upper ({})", unit)),
+                                        );
+                                        *upper = upper.max(*lower);
+                                    }
+                                    let mut breakable = joint.break_force.is_some();
+                                    if ui.checkbox(&mut breakable, "Breakable").changed() {
+                                        joint.break_force = breakable.then_some(50.);
+                                    }
+                                    if let Some(break_force) = &mut joint.break_force {
+                                        ui.add(
+                                            egui::Slider::new(break_force, 0.0..=500.)
+                                                .text("Break force"),
+                                        );
+                                    }
+                                    if ui.button("Remove").clicked() {
+                                        removed = Some(*id);
+                                    }
+                                });
+                            }
+                            if let Some(id) = removed {
+                                app.app_context.entity_manager.remove_joint(&id);
+                            }
+                        });
+                        egui::CollapsingHeader::new("Electrostatics").show(ui, |ui| {
+                            let electrostatics = &mut app.physics_engine.electrostatics;
+                            ui.checkbox(&mut electrostatics.enabled, "Coulomb forces");
+                            ui.checkbox(
+                                &mut app.app_context.show_electric_field,
+                                "Show electric field",
+                            );
+                            // Charges are shown in microcoulombs
+                            let mut charge = app.app_context.current_charge * 1e6;
+                            if ui
+                                .add(
+                                    egui::Slider::new(&mut charge, -50.0..=50.)
+                                        .text("Spawn charge (uC)"),
+                                )
+                                .changed()
+                            {
+                                app.app_context.current_charge = charge * 1e-6;
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label("External field (N/C):");
+                                ui.add(
+                                    egui::DragValue::new(&mut electrostatics.external_field.x)
+                                        .speed(100.),
+                                );
+                                ui.add(
+                                    egui::DragValue::new(&mut electrostatics.external_field.y)
+                                        .speed(100.),
+                                );
+                            });
+
+                            ui.horizontal(|ui| {
+                                let center = app.app_context.physics_dimensions / 2.;
+                                if ui.button("Add + charge").clicked() {
+                                    app.app_context.add_point_charge(center, 10e-6);
+                                }
+                                if ui.button("Add - charge").clicked() {
+                                    app.app_context.add_point_charge(center, -10e-6);
+                                }
+                            });
+                            let point_charges = &mut app.app_context.entity_manager.point_charges;
+                            let mut removed = None;
+                            for (i, point_charge) in point_charges.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("Charge {}", i));
+                                    ui.add(
+                                        egui::DragValue::new(&mut point_charge.position.x)
+                                            .speed(0.05),
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(&mut point_charge.position.y)
+                                            .speed(0.05),
+                                    );
+                                    let mut charge = point_charge.charge * 1e6;
+                                    if ui
+                                        .add(egui::DragValue::new(&mut charge).suffix(" uC"))
+                                        .changed()
+                                    {
+                                        point_charge.charge = charge * 1e-6;
+                                    }
+                                    if ui.button("Remove").clicked() {
+                                        removed = Some(i);
+                                    }
+                                });
+                            }
+                            if let Some(i) = removed {
+                                point_charges.remove(i);
+                            }
+                        });
+                        egui::CollapsingHeader::new("Diagnostics").show(ui, |ui| {
+                            let diagnostics = app.physics_engine.diagnostics();
+                            ui.label(format!("Kinetic energy: {:.3} J", diagnostics.kinetic));
+                            ui.label(format!("Potential energy: {:.3} J", diagnostics.potential));
+                            ui.label(format!("Spring energy: {:.3} J", diagnostics.spring));
+                            ui.label(format!(
+                                "Rotational energy: {:.3} J",
+                                diagnostics.rotational
+                            ));
+                            ui.label(format!("Total energy: {:.3} J", diagnostics.total_energy()));
+                            if diagnostics.motor_work != 0. {
+                                ui.label(format!(
+                                    "Motor work last step: {:.3} J",
+                                    diagnostics.motor_work
+                                ));
+                            }
+                            ui.label(format!(
+                                "Momentum: {:.3}, {:.3}",
+                                diagnostics.linear_momentum.x, diagnostics.linear_momentum.y
+                            ));
+                            ui.label(format!(
+                                "Angular momentum: {:.3}",
+                                diagnostics.angular_momentum
+                            ));
+                        });
+                        egui::CollapsingHeader::new(format!(
+                            "Systems {}",
+                            app.schedule.systems.len()
+                        ))
+                        .show(ui, |ui| {
+                            for stage in Stage::ALL {
+                                let Some(order) = app.schedule.sorted(stage) else {
+                                    continue;
+                                };
+                                if order.is_empty() {
+                                    continue;
+                                }
+                                ui.label(format!("{:?}", stage));
+                                for id in order {
+                                    let system = app.schedule.systems.get_mut(&id).unwrap();
+                                    ui.checkbox(&mut system.enabled, &system.name);
+                                }
+                            }
                         });
+                        ui.horizontal(|ui| {
+                            let boundary = &mut app.app_context.boundary;
+                            ui.label("World boundary:");
+                            ui.radio_value(&mut boundary.mode, BoundaryMode::None, "None");
+                            ui.radio_value(&mut boundary.mode, BoundaryMode::Walls, "Walls");
+                            ui.radio_value(&mut boundary.mode, BoundaryMode::Periodic, "Periodic");
+                        });
+                        if app.app_context.boundary.mode == BoundaryMode::Walls {
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut app.app_context.boundary.restitution,
+                                    0.0..=1.,
+                                )
+                                .text("Wall restitution"),
+                            );
+                        }
                         ui.checkbox(&mut app.app_context.debug_outlines, "Debug Outlines");
                         ui.checkbox(&mut app.app_context.show_forces, "Forces");
                         ui.checkbox(&mut app.app_context.show_com, "Center of Mass");
                         ui.horizontal(|ui| {
+                            let mut threads = app.physics_engine.threads();
+                            ui.label("Physics threads:");
+                            if ui.add(egui::Slider::new(&mut threads, 1..=16)).changed() {
+                                app.physics_engine.set_threads(threads);
+                            }
+                        });
+                        ui.checkbox(&mut app.physics_engine.deterministic, "Deterministic");
+                        if app.physics_engine.deterministic {
+                            ui.label(format!(
+                                "Step {} state hash: {:016x}",
+                                app.physics_engine.steps(),
+                                app.app_context.entity_manager.state_hash()
+                            ));
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label("Integrator:");
+                            for integrator in Integrator::ALL {
+                                if ui
+                                    .add(egui::RadioButton::new(
+                                        app.physics_engine.integrator == integrator,
+                                        integrator.name(),
+                                    ))
+                                    .clicked()
+                                {
+                                    app.physics_engine.integrator = integrator;
+                                };
+                            }
+                        });
+                        let ctx = &mut app.app_context;
+                        ui.horizontal_wrapped(|ui| {
                             ui.label("Current shape:");
-                            if ui
-                                .add(egui::RadioButton::new(
-                                    app.app_context.current_shape == Shape::Circle,
-                                    "Circle",
-                                ))
-                                .clicked()
-                            {
-                                app.app_context.current_shape = Shape::Circle;
+                            let shapes = [
+                                ("Circle", Ok(Shape::Circle)),
+                                ("Rectangle", Ok(Shape::Rectangle)),
+                                ("Triangle", Shape::triangle(ctx.shape_radius)),
+                                (
+                                    "Polygon",
+                                    Shape::regular_polygon(ctx.shape_sides, ctx.shape_radius),
+                                ),
+                                (
+                                    "Capsule",
+                                    Ok(Shape::Capsule {
+                                        radius: ctx.shape_radius,
+                                        length: ctx.shape_length,
+                                    }),
+                                ),
+                            ];
+                            for (name, shape) in shapes {
+                                let selected = shape.as_ref().is_ok_and(|shape| {
+                                    std::mem::discriminant(&ctx.current_shape)
+                                        == std::mem::discriminant(shape)
+                                });
+                                if ui.add(egui::RadioButton::new(selected, name)).clicked() {
+                                    match shape {
+                                        Ok(shape) => ctx.current_shape = shape,
+                                        Err(e) => log::warn!("Could not build {}: {}", name, e),
+                                    }
+                                };
+                            }
+                        });
+                        // Rebuild the selected shape when one of its parameters moves
+                        let mut changed = false;
+                        if matches!(
+                            ctx.current_shape,
+                            Shape::Triangle(_) | Shape::Polygon(_) | Shape::Capsule { .. }
+                        ) {
+                            changed |= ui
+                                .add(
+                                    egui::Slider::new(&mut ctx.shape_radius, 0.05..=1.)
+                                        .text("Radius"),
+                                )
+                                .changed();
+                        }
+                        if let Shape::Polygon(_) = ctx.current_shape {
+                            changed |= ui
+                                .add(egui::Slider::new(&mut ctx.shape_sides, 3..=12).text("Sides"))
+                                .changed();
+                        }
+                        if let Shape::Capsule { .. } = ctx.current_shape {
+                            changed |= ui
+                                .add(
+                                    egui::Slider::new(&mut ctx.shape_length, 0.05..=2.)
+                                        .text("Length"),
+                                )
+                                .changed();
+                        }
+                        if changed {
+                            let shape = match ctx.current_shape {
+                                Shape::Triangle(_) => Shape::triangle(ctx.shape_radius),
+                                Shape::Polygon(_) => {
+                                    Shape::regular_polygon(ctx.shape_sides, ctx.shape_radius)
+                                }
+                                _ => Ok(Shape::Capsule {
+                                    radius: ctx.shape_radius,
+                                    length: ctx.shape_length,
+                                }),
                             };
-                            if ui
-                                .add(egui::RadioButton::new(
-                                    app.app_context.current_shape == Shape::Rectangle,
-                                    "Rectangle",
-                                ))
-                                .clicked()
+                            match shape {
+                                Ok(shape) => ctx.current_shape = shape,
+                                Err(e) => log::warn!("Could not rebuild the shape: {}", e),
+                            }
+                        }
+                        egui::ComboBox::from_label("Material")
+                            .selected_text(app.app_context.current_material.clone())
+                            .show_ui(ui, |ui| {
+                                for material in &app.app_context.materials.materials {
+                                    ui.selectable_value(
+                                        &mut app.app_context.current_material,
+                                        material.name.clone(),
+                                        &material.name,
+                                    );
+                                }
+                            });
+                        let current_material = app.app_context.current_material.clone();
+                        if let Some(material) = app
+                            .app_context
+                            .materials
+                            .materials
+                            .iter_mut()
+                            .find(|m| m.name == current_material)
+                        {
+                            ui.add(
+                                egui::Slider::new(&mut material.density, 0.01..=20.)
+                                    .text("Density"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut material.restitution, 0.0..=1.)
+                                    .text("Restitution"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut material.friction, 0.0..=1.)
+                                    .text("Friction"),
+                            );
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Save materials").clicked()
+                                && let Err(e) = app.app_context.materials.save(MATERIALS_PATH)
                             {
-                                app.app_context.current_shape = Shape::Rectangle;
-                            };
+                                log::error!("Could not save materials: {}", e);
+                            }
+                            if ui.button("Reload materials").clicked() {
+                                match MaterialLibrary::load(MATERIALS_PATH) {
+                                    Ok(materials) => app.app_context.materials = materials,
+                                    Err(e) => log::error!("Could not load materials: {}", e),
+                                }
+                            }
                         });
                         let mouse_pos_sim = app.app_context.get_mouse_position();
                         let mouse_pos_real = mouse_position();
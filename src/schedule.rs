@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+
+use crate::app::AppContext;
+
+// When in a frame a system runs, in the order they run in. Frame stages are given the wall
+// clock time since the last frame, FixedUpdate and PostPhysics the simulated time
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum Stage {
+    // Once, before the first frame
+    Startup,
+    // Before the UI and the physics update, with the frame time
+    PrePhysics,
+    // Before every physics step, with that step's dt. Runs several times per frame when the
+    // time scale is above 1 and not at all while paused
+    FixedUpdate,
+    // After the physics update, events raised by it can be read here. Gets the simulated
+    // time that passed this frame: zero while paused, several steps when catching up
+    PostPhysics,
+    // After the screen is cleared, before the world is drawn, with the frame time
+    PreRender,
+    // After the world is drawn, before the UI is drawn on top, with the frame time
+    PostRender,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Startup,
+        Stage::PrePhysics,
+        Stage::FixedUpdate,
+        Stage::PostPhysics,
+        Stage::PreRender,
+        Stage::PostRender,
+    ];
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct SystemId(pub usize);
+
+pub type SystemFn<S> = Box<dyn FnMut(&mut AppContext, f32, &mut S)>;
+
+pub struct System<S> {
+    pub name: String,
+    pub stage: Stage,
+    // Disabled systems are skipped but keep their place in the order
+    pub enabled: bool,
+    function: SystemFn<S>,
+}
+
+// Systems grouped by stage. Within a stage they run in the order they were added, unless
+// `order` says otherwise
+pub struct Schedule<S> {
+    pub systems: BTreeMap<SystemId, System<S>>,
+    // (first, then) pairs
+    constraints: Vec<(SystemId, SystemId)>,
+    // Run order of each stage, sorted again after a system is added or removed or the
+    // constraints change
+    order_cache: BTreeMap<Stage, Vec<SystemId>>,
+    curr_system_id: usize,
+}
+
+impl<S> Schedule<S> {
+    pub fn init() -> Self {
+        Self {
+            systems: BTreeMap::new(),
+            constraints: vec![],
+            order_cache: BTreeMap::new(),
+            curr_system_id: 0,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        stage: Stage,
+        name: &str,
+        function: impl FnMut(&mut AppContext, f32, &mut S) + 'static,
+    ) -> SystemId {
+        let system_id = SystemId(self.curr_system_id);
+        self.curr_system_id += 1;
+        self.order_cache.remove(&stage);
+        self.systems.insert(
+            system_id,
+            System {
+                name: name.to_owned(),
+                stage,
+                enabled: true,
+                function: Box::new(function),
+            },
+        );
+        system_id
+    }
+
+    pub fn remove(&mut self, id: &SystemId) -> Option<System<S>> {
+        self.constraints
+            .retain(|(first, then)| first != id && then != id);
+        let system = self.systems.remove(id)?;
+        self.order_cache.remove(&system.stage);
+        Some(system)
+    }
+
+    // First system with this name
+    pub fn find(&self, name: &str) -> Option<SystemId> {
+        self.systems
+            .iter()
+            .find(|(_, system)| system.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn set_enabled(&mut self, id: &SystemId, enabled: bool) {
+        if let Some(system) = self.systems.get_mut(id) {
+            system.enabled = enabled;
+        }
+    }
+
+    // Makes `first` run before `then`. Both have to be in the same stage, and the constraint
+    // is refused if it would make a cycle
+    pub fn order(&mut self, first: SystemId, then: SystemId) -> Result<(), String> {
+        let (Some(a), Some(b)) = (self.systems.get(&first), self.systems.get(&then)) else {
+            return Err("No such system".to_owned());
+        };
+        if a.stage != b.stage {
+            return Err(format!(
+                "{} runs in {:?} and {} in {:?}, only systems in the same stage can be ordered",
+                a.name, a.stage, b.name, b.stage
+            ));
+        }
+        let stage = a.stage;
+
+        self.constraints.push((first, then));
+        if self.sorted(stage).is_none() {
+            let (a, b) = (&self.systems[&first].name, &self.systems[&then].name);
+            let error = format!("Running {} before {} would make a cycle", a, b);
+            self.constraints.pop();
+            return Err(error);
+        }
+        self.order_cache.remove(&stage);
+        Ok(())
+    }
+
+    // The systems of a stage in the order they run, None if the constraints make a cycle.
+    // Ties go to the system added first
+    pub fn sorted(&self, stage: Stage) -> Option<Vec<SystemId>> {
+        Self::sort(&self.systems, &self.constraints, stage)
+    }
+
+    fn sort(
+        systems: &BTreeMap<SystemId, System<S>>,
+        constraints: &[(SystemId, SystemId)],
+        stage: Stage,
+    ) -> Option<Vec<SystemId>> {
+        let mut incoming: BTreeMap<SystemId, usize> = systems
+            .iter()
+            .filter(|(_, system)| system.stage == stage)
+            .map(|(id, _)| (*id, 0))
+            .collect();
+        for (_, then) in constraints {
+            if let Some(count) = incoming.get_mut(then) {
+                *count += 1;
+            }
+        }
+
+        let mut sorted = vec![];
+        while let Some(id) = incoming
+            .iter()
+            .find(|(_, count)| **count == 0)
+            .map(|(id, _)| *id)
+        {
+            incoming.remove(&id);
+            for (_, then) in constraints.iter().filter(|(first, _)| *first == id) {
+                if let Some(count) = incoming.get_mut(then) {
+                    *count -= 1;
+                }
+            }
+            sorted.push(id);
+        }
+        incoming.is_empty().then_some(sorted)
+    }
+
+    // Runs the enabled systems of a stage
+    pub fn run(&mut self, stage: Stage, app_context: &mut AppContext, dt: f32, state: &mut S) {
+        let order = self.order_cache.entry(stage).or_insert_with(|| {
+            Self::sort(&self.systems, &self.constraints, stage)
+                .expect("Ordering constraints are checked for cycles when added")
+        });
+        for id in order.iter() {
+            let system = self.systems.get_mut(id).unwrap();
+            if system.enabled {
+                (system.function)(app_context, dt, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::math::Vec2f;
+
+    type Log = Vec<&'static str>;
+
+    fn add(schedule: &mut Schedule<Log>, name: &'static str) -> SystemId {
+        schedule.add(Stage::PrePhysics, name, move |_, _, log: &mut Log| {
+            log.push(name)
+        })
+    }
+
+    fn run(schedule: &mut Schedule<Log>) -> Log {
+        let mut app_context = AppContext::init(Vec2f::new(15., 10.));
+        let mut log = vec![];
+        schedule.run(Stage::PrePhysics, &mut app_context, 0., &mut log);
+        log
+    }
+
+    #[test]
+    fn constraints_order_systems() {
+        let mut schedule = Schedule::init();
+        let a = add(&mut schedule, "a");
+        let b = add(&mut schedule, "b");
+        let c = add(&mut schedule, "c");
+        let d = add(&mut schedule, "d");
+        // Unconstrained systems keep the order they were added in
+        assert_eq!(run(&mut schedule), ["a", "b", "c", "d"]);
+        schedule.order(d, a).unwrap();
+        schedule.order(c, b).unwrap();
+        assert_eq!(run(&mut schedule), ["c", "b", "d", "a"]);
+        // Other stages are left alone
+        schedule.add(Stage::PostPhysics, "e", |_, _, log: &mut Log| log.push("e"));
+        assert_eq!(schedule.sorted(Stage::PostPhysics).unwrap().len(), 1);
+        assert!(schedule.order(a, SystemId(4)).is_err());
+    }
+
+    #[test]
+    fn cycles_are_refused() {
+        let mut schedule = Schedule::init();
+        let a = add(&mut schedule, "a");
+        let b = add(&mut schedule, "b");
+        let c = add(&mut schedule, "c");
+        schedule.order(b, a).unwrap();
+        schedule.order(c, b).unwrap();
+        assert!(schedule.order(a, c).is_err());
+        assert!(schedule.order(a, a).is_err());
+        // The refused constraint was not kept
+        assert_eq!(run(&mut schedule), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn disabled_systems_are_skipped() {
+        let mut schedule = Schedule::init();
+        let a = add(&mut schedule, "a");
+        let b = add(&mut schedule, "b");
+        schedule.order(b, a).unwrap();
+        schedule.set_enabled(&b, false);
+        assert_eq!(run(&mut schedule), ["a"]);
+        // And keep their place when enabled again
+        schedule.set_enabled(&b, true);
+        assert_eq!(run(&mut schedule), ["b", "a"]);
+    }
+
+    #[test]
+    fn cached_order_follows_changes() {
+        let mut schedule = Schedule::init();
+        let a = add(&mut schedule, "a");
+        let b = add(&mut schedule, "b");
+        assert_eq!(run(&mut schedule), ["a", "b"]);
+        schedule.order(b, a).unwrap();
+        assert_eq!(run(&mut schedule), ["b", "a"]);
+        let c = add(&mut schedule, "c");
+        assert_eq!(run(&mut schedule), ["b", "a", "c"]);
+        schedule.remove(&b);
+        assert_eq!(run(&mut schedule), ["a", "c"]);
+        schedule.order(c, a).unwrap();
+        assert_eq!(run(&mut schedule), ["c", "a"]);
+    }
+}
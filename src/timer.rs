@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::app::AppContext;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TimerMode {
+    // Goes off once, `duration` after it was started
+    Once,
+    // Goes off every `duration` seconds
+    Repeating,
+    // Ready straight away, then not again until `duration` after each trigger
+    Cooldown,
+}
+
+// Measures time on whatever clock `now` comes from. With AppContext::time timers stop while
+// the simulation is paused and follow the time scale, with the wall clock they do not, which
+// suits debouncing input
+#[derive(Clone, Copy, Debug)]
+pub struct Timer {
+    pub mode: TimerMode,
+    pub duration: f32,
+    // When the current period started
+    start: f32,
+    // A one-shot that has already gone off
+    done: bool,
+}
+
+impl Timer {
+    pub fn once(duration: f32, now: f32) -> Self {
+        Self {
+            mode: TimerMode::Once,
+            duration,
+            start: now,
+            done: false,
+        }
+    }
+
+    pub fn repeating(period: f32, now: f32) -> Self {
+        Self {
+            mode: TimerMode::Repeating,
+            duration: period,
+            start: now,
+            done: false,
+        }
+    }
+
+    pub fn cooldown(duration: f32) -> Self {
+        Self {
+            mode: TimerMode::Cooldown,
+            duration,
+            start: f32::NEG_INFINITY,
+            done: false,
+        }
+    }
+
+    // Seconds into the current period. Zero if time was rewound to before it started
+    pub fn elapsed(&self, now: f32) -> f32 {
+        (now - self.start).max(0.)
+    }
+
+    pub fn remaining(&self, now: f32) -> f32 {
+        (self.duration - self.elapsed(now)).max(0.)
+    }
+
+    // Whether the current period is over. For a cooldown this means it is ready
+    pub fn finished(&self, now: f32) -> bool {
+        self.elapsed(now) >= self.duration
+    }
+
+    // How many times the timer went off since the last tick. At most once for a one-shot,
+    // once per period that passed for a repeating timer. Cooldowns only go off by `trigger`
+    pub fn tick(&mut self, now: f32) -> u32 {
+        match self.mode {
+            TimerMode::Once => {
+                if self.done || !self.finished(now) {
+                    return 0;
+                }
+                self.done = true;
+                1
+            }
+            TimerMode::Repeating => {
+                if self.duration <= 0. {
+                    return 0;
+                }
+                let periods = (self.elapsed(now) / self.duration).floor();
+                self.start += periods * self.duration;
+                periods as u32
+            }
+            TimerMode::Cooldown => 0,
+        }
+    }
+
+    // Uses a cooldown if it is ready and starts it again. False if it is still cooling down.
+    // Other timers are restarted
+    pub fn trigger(&mut self, now: f32) -> bool {
+        if self.mode == TimerMode::Cooldown && !self.finished(now) {
+            return false;
+        }
+        self.restart(now);
+        true
+    }
+
+    pub fn restart(&mut self, now: f32) {
+        self.start = now;
+        self.done = false;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct TaskId(pub usize);
+
+pub type TaskFn = Box<dyn FnMut(&mut AppContext)>;
+
+struct Task {
+    // Simulated time of the next run
+    at: f32,
+    period: Option<f32>,
+    callback: TaskFn,
+}
+
+// Callbacks run at a simulated time or every so many simulated seconds, checked after every
+// physics step
+pub struct Scheduler {
+    tasks: BTreeMap<TaskId, Task>,
+    // Tasks taken out while they run. A callback cancelling one takes it off this list
+    running: HashSet<TaskId>,
+    curr_task_id: usize,
+}
+
+impl Scheduler {
+    pub fn init() -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            running: HashSet::new(),
+            curr_task_id: 0,
+        }
+    }
+
+    fn add(&mut self, at: f32, period: Option<f32>, callback: TaskFn) -> TaskId {
+        let task_id = TaskId(self.curr_task_id);
+        self.curr_task_id += 1;
+        self.tasks.insert(
+            task_id,
+            Task {
+                at,
+                period,
+                callback,
+            },
+        );
+        task_id
+    }
+
+    // Runs once the simulation reaches `time`
+    pub fn at(&mut self, time: f32, callback: impl FnMut(&mut AppContext) + 'static) -> TaskId {
+        self.add(time, None, Box::new(callback))
+    }
+
+    // Runs every `period` seconds, the first time one period after `now`
+    pub fn every(
+        &mut self,
+        period: f32,
+        now: f32,
+        callback: impl FnMut(&mut AppContext) + 'static,
+    ) -> TaskId {
+        self.add(now + period, Some(period), Box::new(callback))
+    }
+
+    // False if the task already ran or was cancelled
+    pub fn cancel(&mut self, id: &TaskId) -> bool {
+        self.tasks.remove(id).is_some() || self.running.remove(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // Runs every task that is due by `app_context.time`, earliest first. A repeating task runs
+    // once for each period that passed
+    pub fn run(app_context: &mut AppContext) {
+        let mut tasks = std::mem::take(&mut app_context.scheduler.tasks);
+        app_context.scheduler.running = tasks.keys().copied().collect();
+        loop {
+            tasks.retain(|id, _| app_context.scheduler.running.contains(id));
+            let due = tasks
+                .iter()
+                .filter(|(_, task)| task.at <= app_context.time)
+                .min_by(|(a_id, a), (b_id, b)| a.at.total_cmp(&b.at).then(a_id.cmp(b_id)))
+                .map(|(id, _)| *id);
+            let Some(id) = due else {
+                break;
+            };
+
+            let task = tasks.get_mut(&id).unwrap();
+            (task.callback)(app_context);
+            match task.period {
+                Some(period) if period > 0. => task.at += period,
+                _ => {
+                    app_context.scheduler.running.remove(&id);
+                }
+            }
+        }
+
+        let scheduler = &mut app_context.scheduler;
+        scheduler.tasks.extend(tasks);
+        scheduler.running.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::math::math::Vec2f;
+
+    #[test]
+    fn one_shot_fires_once() {
+        let mut timer = Timer::once(1., 0.);
+        assert_eq!(timer.tick(0.5), 0);
+        assert_eq!(timer.tick(1.), 1);
+        assert_eq!(timer.tick(1.5), 0);
+        assert_eq!(timer.tick(10.), 0);
+        timer.restart(10.);
+        assert_eq!(timer.tick(11.), 1);
+    }
+
+    #[test]
+    fn repeating_timer_catches_up() {
+        let mut timer = Timer::repeating(0.5, 0.);
+        assert_eq!(timer.tick(0.4), 0);
+        // A long frame covers several periods
+        assert_eq!(timer.tick(1.6), 3);
+        // The leftover 0.1 s is kept for the next period
+        assert_eq!(timer.tick(1.95), 0);
+        assert_eq!(timer.tick(2.), 1);
+    }
+
+    #[test]
+    fn cooldown_is_ready_then_waits() {
+        let mut timer = Timer::cooldown(0.2);
+        assert!(timer.trigger(5.));
+        assert!(!timer.trigger(5.1));
+        assert!(timer.trigger(5.25));
+    }
+
+    // An app context whose scheduled callbacks write to a shared log
+    fn context() -> (AppContext, Rc<RefCell<Vec<&'static str>>>) {
+        (AppContext::init(Vec2f::new(15., 10.)), Rc::default())
+    }
+
+    #[test]
+    fn cancel_from_a_running_callback() {
+        let (mut app_context, log) = context();
+        let later = Rc::new(RefCell::new(None));
+        let (first_log, taken) = (log.clone(), later.clone());
+        app_context.after(1., move |app_context| {
+            first_log.borrow_mut().push("first");
+            let id = taken.borrow_mut().take().unwrap();
+            assert!(app_context.scheduler.cancel(&id));
+        });
+        let second_log = log.clone();
+        let id = app_context.every(0.5, move |_| second_log.borrow_mut().push("second"));
+        *later.borrow_mut() = Some(id);
+
+        // The repeating task is due twice and runs once before it is cancelled
+        app_context.time = 1.;
+        Scheduler::run(&mut app_context);
+        assert_eq!(*log.borrow(), ["second", "first"]);
+        app_context.time = 3.;
+        Scheduler::run(&mut app_context);
+        assert_eq!(log.borrow().len(), 2);
+        assert!(app_context.scheduler.is_empty());
+        assert!(!app_context.scheduler.cancel(&id));
+    }
+
+    #[test]
+    fn tasks_added_while_running_are_kept() {
+        let (mut app_context, log) = context();
+        let outer = log.clone();
+        app_context.after(1., move |app_context| {
+            outer.borrow_mut().push("outer");
+            let inner = outer.clone();
+            app_context.after(1., move |_| inner.borrow_mut().push("inner"));
+        });
+        app_context.time = 1.;
+        Scheduler::run(&mut app_context);
+        assert_eq!(*log.borrow(), ["outer"]);
+        assert_eq!(app_context.scheduler.len(), 1);
+        app_context.time = 2.;
+        Scheduler::run(&mut app_context);
+        assert_eq!(*log.borrow(), ["outer", "inner"]);
+        assert!(app_context.scheduler.is_empty());
+    }
+}